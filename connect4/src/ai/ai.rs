@@ -1,15 +1,14 @@
-use crate::game_logic::{Cell, Connect4, BOARD_HEIGHT, BOARD_WIDTH};
-use std::{
-    sync::{Arc, Mutex},
-    thread,
-};
+use crate::game_logic::{Cell, Connect4, Player, BOARD_HEIGHT, BOARD_WIDTH};
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
 
 // Conditionnal compilation : different default depth value when compiling in debug or release mode
 #[cfg(debug_assertions)]
-pub const DEFAULT_DEPTH: &'static str = "8";
+pub const DEFAULT_DEPTH: &str = "8";
 
 #[cfg(not(debug_assertions))]
-pub const DEFAULT_DEPTH: &'static str = "9";
+pub const DEFAULT_DEPTH: &str = "9";
 
 // Score values for each cell
 const VALUES: [i32; BOARD_WIDTH * BOARD_HEIGHT] = [
@@ -88,6 +87,22 @@ impl AlphaBeta {
     }
 }
 
+// Start the search of the position after a move of the ai, in its own thread. Returns a function
+// waiting for its score.
+#[cfg(not(target_arch = "wasm32"))]
+fn search(search: impl FnOnce() -> i32 + Send + 'static) -> impl FnOnce() -> i32 {
+    let handle = thread::spawn(search);
+    move || handle.join().unwrap()
+}
+
+// Browsers have no threads: the searches of the moves are run one after the other, still sharing
+// their bounds
+#[cfg(target_arch = "wasm32")]
+fn search(search: impl FnOnce() -> i32) -> impl FnOnce() -> i32 {
+    let score = search();
+    move || score
+}
+
 // chose the best move with a multi-threaded minimax algorithm with alpha-beta pruning
 pub fn ai_action(game: Connect4, depth: usize) -> usize {
     let alpha_beta = AlphaBeta(-i32::MAX, i32::MAX);
@@ -98,18 +113,52 @@ pub fn ai_action(game: Connect4, depth: usize) -> usize {
             let mut game = game.clone();
             game.play(action);
             let alpha_beta = Arc::clone(&alpha_beta);
-            let handle = thread::spawn(move || alpha_beta_search(game, depth - 1, alpha_beta, Min));
+            let handle = search(move || alpha_beta_search(game, depth - 1, alpha_beta, Min));
             handles.push((handle, action));
         }
     }
     handles
         .into_iter()
-        .map(|(handle, action)| (handle.join().unwrap(), action))
+        .map(|(handle, action)| (handle(), action))
         .max()
         .unwrap()
         .1
 }
 
+// Scores of a won and of a lost position, whatever the opponent plays
+pub const WIN: i32 = i32::MAX;
+pub const LOSS: i32 = -i32::MAX;
+
+// Score of each column for the player to move, None for the full columns. Unlike in ai_action,
+// the bounds of the search belong to each position so that all the scores are exact.
+pub fn column_scores(game: &Connect4, depth: usize) -> [Option<i32>; BOARD_WIDTH] {
+    assert!(depth >= 1, "Unvalid depth of search: {}", depth);
+    let player = game.to_play;
+    let mut handles = Vec::with_capacity(BOARD_WIDTH);
+    for action in 0..BOARD_WIDTH {
+        if game.valid_action(action) {
+            let mut game = game.clone();
+            game.play(action);
+            let handle = search(move || exact_search(game, depth - 1, (LOSS, WIN), Min, player));
+            handles.push((handle, action));
+        }
+    }
+    let mut scores = [None; BOARD_WIDTH];
+    for (handle, action) in handles {
+        scores[action] = Some(handle());
+    }
+    scores
+}
+
+// Score as shown to the players
+pub fn describe_score(score: i32) -> String {
+    match score {
+        WIN => String::from("win"),
+        LOSS => String::from("loss"),
+        _ => format!("{:+}", score),
+    }
+}
+
 // recursive procedure for the minimax algorithm with alpha-beta pruning
 fn alpha_beta_search(
     game: Connect4,
@@ -117,7 +166,7 @@ fn alpha_beta_search(
     alpha_beta: SharedAlphaBeta,
     min_max: MinMax,
 ) -> i32 {
-    if let Some(_) = game.check_winner() {
+    if game.check_winner().is_some() {
         return min_max.default_value();
     }
     if game.check_full() {
@@ -145,12 +194,60 @@ fn alpha_beta_search(
     best
 }
 
+// Minimax algorithm with alpha-beta pruning, each position having its own bounds. The scores are
+// the ones of the given player, who moves in the Max positions.
+fn exact_search(
+    game: Connect4,
+    depth: usize,
+    (mut alpha, mut beta): (i32, i32),
+    min_max: MinMax,
+    player: Player,
+) -> i32 {
+    if game.check_winner().is_some() {
+        return min_max.default_value();
+    }
+    if game.check_full() {
+        return 0;
+    }
+    if depth == 0 {
+        return evaluate_for(&game, player);
+    }
+    let mut best = min_max.default_value();
+    for &action in PRIORITY_MOVES.iter() {
+        if game.valid_action(action) {
+            let mut game = game.clone();
+            game.play(action);
+            let score = exact_search(game, depth - 1, (alpha, beta), min_max.other(), player);
+            match min_max {
+                Max => {
+                    best = best.max(score);
+                    alpha = alpha.max(best);
+                }
+                Min => {
+                    best = best.min(score);
+                    beta = beta.min(best);
+                }
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+    }
+    best
+}
+
+// Evaluate a board for the player who played the last move
+#[inline]
+fn evaluate(game: Connect4) -> i32 {
+    evaluate_for(&game, game.to_play.other())
+}
+
 // Evaluate a board using the VALUES. This is a simplistic evaluation function. Our aim was to
 // implement a multi-threaded minimax algorithm with alpha-beta pruning, not to make a
 // high-performance ai.
 #[inline]
-fn evaluate(game: Connect4) -> i32 {
-    let player = Cell::from(game.to_play.other());
+fn evaluate_for(game: &Connect4, player: Player) -> i32 {
+    let player = Cell::from(player);
     let mut score = 0;
     for (&val, &cell) in VALUES.iter().zip(game.board.iter()) {
         if cell == player {
@@ -1,61 +1,22 @@
 use crate::{
-    ai, client,
-    communication::Message::{self, *},
-    game_logic::Connect4,
-    server,
+    client::{self, Appearance},
+    communication::transport::{Channel, ChannelEnd},
+    server::{self, LocalPlayer},
 };
 use rand::Rng;
-use std::{
-    marker,
-    net::TcpStream,
-    thread::{self, JoinHandle},
-    time,
-};
+use std::thread;
 
-// starts the local server, the user client and the ai client
-pub fn play_against(socket_address: (String, u16), depth: usize, save_replay: Option<String>) {
-    let address = socket_address.clone();
-    thread::spawn(|| server::run(address));
-    thread::sleep(time::Duration::from_millis(500));
-    let address = socket_address.clone();
-    let run_client = || client::run(address, save_replay);
-    let run_ai_client = move || self::run(socket_address, depth);
-    let (handle_1, handle_2) = if rand::thread_rng().gen() {
-        launch_clients(run_client, run_ai_client)
+// Play against the ai in the same process: the game is run by the server logic in another thread,
+// the first player is chosen randomly
+pub fn play_against(depth: usize, save_replay: Option<String>, appearance: Appearance) {
+    let (client_end, server_end) = ChannelEnd::pair();
+    let (human, ai) = (LocalPlayer::Client(server_end), LocalPlayer::Bot(depth));
+    let players = if rand::thread_rng().gen() {
+        (human, ai)
     } else {
-        launch_clients(run_ai_client, run_client)
+        (ai, human)
     };
-    handle_1.join().unwrap();
-    handle_2.join().unwrap();
-}
-
-// helper function for launching the clients independently of the order
-fn launch_clients<F, G, T>(client_1: F, client_2: G) -> (JoinHandle<T>, JoinHandle<T>)
-where
-    T: marker::Send + 'static,
-    F: marker::Send + FnOnce() -> T + 'static,
-    G: marker::Send + FnOnce() -> T + 'static,
-{
-    let handle_1 = thread::spawn(client_1);
-    thread::sleep(time::Duration::from_millis(500));
-    let handle_2 = thread::spawn(client_2);
-    (handle_1, handle_2)
-}
-
-// Run the client for the ai
-fn run(socket_address: (String, u16), depth: usize) {
-    let mut server = TcpStream::connect(socket_address).unwrap();
-    Message::receive_from(&mut server);
-    let mut game = Connect4::new();
-    loop {
-        match Message::receive_from(&mut server) {
-            Play => {
-                let action = ai::action(game.clone(), depth);
-                Action(action as u8).send_to(&mut server);
-            }
-            ValidAction(action) => game.play(action as usize),
-            Lose | Draw | Win => break,
-            message => panic!("Unexpected message: {:?}", message),
-        }
-    }
+    let game = thread::spawn(|| server::play_local(players, server::Settings::default()));
+    client::run_local(Channel::from(client_end), save_replay, appearance);
+    game.join().unwrap();
 }
@@ -1,5 +1,7 @@
 mod ai;
+#[cfg(not(target_arch = "wasm32"))]
 mod client;
 
-pub use ai::{ai_action as action, DEFAULT_DEPTH};
+pub use ai::{ai_action as action, column_scores, describe_score, DEFAULT_DEPTH, LOSS, WIN};
+#[cfg(not(target_arch = "wasm32"))]
 pub use client::play_against;
@@ -0,0 +1,296 @@
+use crate::{
+    communication::Termination,
+    game_logic::Player::{self, *},
+};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::task;
+use tracing::error;
+
+// Maximum number of games sent for a query, the most recent ones
+pub const MAX_QUERY_RESULTS: usize = 100;
+
+// Game finished on the server. Times are in seconds since the Unix epoch.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GameRecord {
+    pub number: u32,
+    pub players: (String, String), // accounts of red and yellow, empty for guests
+    pub start: u64,
+    pub end: u64,
+    pub winner: Option<Player>,
+    pub termination: Termination,
+    pub moves: Vec<u8>,
+}
+
+// Result of a game, as searched in the archive
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    RedWins,
+    YellowWins,
+    Draw,
+}
+
+impl Outcome {
+    // Adjourned games have no winner but are not draws, they have no outcome
+    pub fn of(game: &GameRecord) -> Option<Self> {
+        match (game.winner, game.termination) {
+            (Some(Red), _) => Some(Outcome::RedWins),
+            (Some(Yellow), _) => Some(Outcome::YellowWins),
+            (None, Termination::Adjourned) => None,
+            (None, _) => Some(Outcome::Draw),
+        }
+    }
+}
+
+// Criteria of a search in the archive, a game has to match all of them
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Query {
+    pub player: Option<String>, // account of one of the players
+    pub from: Option<u64>,      // games which ended at or after this time
+    pub to: Option<u64>,        // games which ended before this time
+    pub outcome: Option<Outcome>,
+    pub opening: Vec<u8>, // first moves of the game
+}
+
+impl Query {
+    pub fn matches(&self, game: &GameRecord) -> bool {
+        self.player
+            .as_ref()
+            .is_none_or(|player| *player == game.players.0 || *player == game.players.1)
+            && self.from.is_none_or(|from| game.end >= from)
+            && self.to.is_none_or(|to| game.end < to)
+            && self
+                .outcome
+                .is_none_or(|outcome| Some(outcome) == Outcome::of(game))
+            && game.moves.starts_with(&self.opening)
+    }
+}
+
+// Current time in seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+// Time of the start of a day given as YYYY-MM-DD, in seconds since the Unix epoch (UTC)
+pub fn parse_date(date: &str) -> Option<u64> {
+    let mut fields = date.trim().splitn(3, '-');
+    let year: i64 = fields.next()?.parse().ok()?;
+    let month: i64 = fields.next()?.parse().ok()?;
+    let day: i64 = fields.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days since the epoch of the proleptic Gregorian calendar, with years starting in March
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    u64::try_from(days * 86_400).ok()
+}
+
+// Date and time (UTC) of a time in seconds since the Unix epoch, as YYYY-MM-DD HH:MM
+pub fn format_time(time: u64) -> String {
+    let (days, seconds) = ((time / 86_400) as i64 + 719_468, time % 86_400);
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds / 60 % 60
+    )
+}
+
+struct Games {
+    writer: Option<Sender<GameRecord>>, // games are only kept in memory without a file
+    games: Vec<GameRecord>,
+}
+
+// Games finished on the server, saved in a text file with one line per game: number, accounts of
+// red and yellow, start and end times, winner, termination and moves, separated by tabs
+#[derive(Clone)]
+pub struct Archive(Arc<Mutex<Games>>);
+
+impl Archive {
+    // Read the games already archived in the file, which is created when the first game ends. It
+    // must be called by a task of a runtime, in which the file is written.
+    pub fn load(file: Option<PathBuf>) -> Self {
+        let mut games = Vec::new();
+        if let Some(content) = file.as_ref().and_then(|file| fs::read_to_string(file).ok()) {
+            for line in content.lines().filter(|line| !line.is_empty()) {
+                games.push(
+                    parse_record(line)
+                        .unwrap_or_else(|| panic!("Unvalid line in the archive: {}", line)),
+                );
+            }
+        }
+        // Writing to the disk blocks, so it is done by a thread for blocking tasks
+        let writer = file.map(|file| {
+            let (sender, receiver) = mpsc::channel();
+            task::spawn_blocking(move || write_games(file, receiver));
+            sender
+        });
+        Self(Arc::new(Mutex::new(Games { writer, games })))
+    }
+
+    // Add a finished game to the archive, its number is given by the archive
+    pub fn record(&self, mut game: GameRecord) {
+        let mut games = self.0.lock().unwrap();
+        game.number = games.games.len() as u32 + 1;
+        if let Some(writer) = &games.writer {
+            let _ = writer.send(game.clone());
+        }
+        games.games.push(game);
+    }
+
+    // Most recent games matching the query, the most recent first
+    pub fn search(&self, query: &Query) -> Vec<GameRecord> {
+        self.0
+            .lock()
+            .unwrap()
+            .games
+            .iter()
+            .rev()
+            .filter(|game| query.matches(game))
+            .take(MAX_QUERY_RESULTS)
+            .cloned()
+            .collect()
+    }
+}
+
+// Append the games to the file in the order in which they are received, until the archive is
+// dropped
+fn write_games(file: PathBuf, games: Receiver<GameRecord>) {
+    for game in games {
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file)
+            .and_then(|mut file| file.write_all(format_record(&game).as_bytes()));
+        if let Err(error) = written {
+            error!(%error, game = game.number, "unable to archive the game");
+        }
+    }
+}
+
+fn format_record(game: &GameRecord) -> String {
+    let winner = match game.winner {
+        Some(winner) => winner.to_string(),
+        None => String::from("draw"),
+    };
+    let termination = match game.termination {
+        Termination::Normal => "normal",
+        Termination::TimeForfeit => "time",
+        Termination::Abandoned => "abandoned",
+        Termination::Adjourned => "adjourned",
+        Termination::Resignation => "resignation",
+        Termination::Agreement => "agreement",
+    };
+    let moves: String = game.moves.iter().map(|action| action.to_string()).collect();
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        game.number,
+        game.players.0,
+        game.players.1,
+        game.start,
+        game.end,
+        winner,
+        termination,
+        moves
+    )
+}
+
+fn parse_record(line: &str) -> Option<GameRecord> {
+    let fields: Vec<_> = line.split('\t').collect();
+    if fields.len() != 8 {
+        return None;
+    }
+    let winner = match fields[5] {
+        "red" => Some(Red),
+        "yellow" => Some(Yellow),
+        "draw" => None,
+        _ => return None,
+    };
+    let termination = match fields[6] {
+        "normal" => Termination::Normal,
+        "time" => Termination::TimeForfeit,
+        "abandoned" => Termination::Abandoned,
+        "adjourned" => Termination::Adjourned,
+        "resignation" => Termination::Resignation,
+        "agreement" => Termination::Agreement,
+        _ => return None,
+    };
+    let moves = fields[7]
+        .chars()
+        .map(|action| action.to_digit(10).map(|action| action as u8))
+        .collect::<Option<_>>()?;
+    Some(GameRecord {
+        number: fields[0].parse().ok()?,
+        players: (fields[1].to_owned(), fields[2].to_owned()),
+        start: fields[3].parse().ok()?,
+        end: fields[4].parse().ok()?,
+        winner,
+        termination,
+        moves,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(winner: Option<Player>, termination: Termination) -> GameRecord {
+        GameRecord {
+            number: 1,
+            players: (String::from("alice"), String::from("bob")),
+            start: 0,
+            end: 60,
+            winner,
+            termination,
+            moves: vec![3, 3, 2],
+        }
+    }
+
+    #[test]
+    fn adjourned_games_are_not_draws() {
+        let draws = Query {
+            outcome: Some(Outcome::Draw),
+            ..Query::default()
+        };
+        assert!(draws.matches(&game(None, Termination::Normal)));
+        assert!(draws.matches(&game(None, Termination::Agreement)));
+        assert!(!draws.matches(&game(None, Termination::Adjourned)));
+        assert!(Query::default().matches(&game(None, Termination::Adjourned)));
+    }
+
+    #[test]
+    fn winners_match_their_outcome() {
+        let red_wins = Query {
+            outcome: Some(Outcome::RedWins),
+            ..Query::default()
+        };
+        assert!(red_wins.matches(&game(Some(Red), Termination::Resignation)));
+        assert!(!red_wins.matches(&game(Some(Yellow), Termination::Normal)));
+    }
+}
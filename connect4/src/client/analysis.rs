@@ -0,0 +1,194 @@
+use super::{
+    board::{self, Appearance},
+    command::Command,
+};
+use crate::{
+    ai,
+    game_logic::{Cell, Connect4, Player, BOARD_WIDTH},
+};
+
+// Depth of the searches answering the players during their games, so that they do not wait for
+// long while their clock is running
+const HINT_DEPTH: usize = 7;
+
+pub const COMMANDS: &str = "hint (best column according to the AI), eval (score of each column), \
+    threats (columns in which a player would win right away)";
+
+// Answer a command asking for the help of the AI on the position, for the player to move, with the
+// columns numbered from the given first column. Returns None for the other commands.
+pub fn answer(command: &Command, game: &Connect4, first_column: usize) -> Option<String> {
+    match command {
+        Command::Hint => {
+            let (score, best) = best_column(&ai::column_scores(game, HINT_DEPTH))?;
+            Some(format!(
+                "The AI suggests column {} ({}).",
+                best + first_column,
+                ai::describe_score(score)
+            ))
+        }
+        Command::Eval => {
+            let scores = ai::column_scores(game, HINT_DEPTH);
+            Some(format!(
+                "Scores of the columns for the {} player (depth {}): {}.",
+                game.to_play,
+                HINT_DEPTH,
+                describe_scores(&scores, first_column)
+            ))
+        }
+        Command::Threats => {
+            let threats: Vec<String> = [game.to_play, game.to_play.other()]
+                .into_iter()
+                .map(|player| {
+                    let columns: Vec<String> = winning_columns(game, player)
+                        .iter()
+                        .map(|column| (column + first_column).to_string())
+                        .collect();
+                    match columns.len() {
+                        0 => format!("the {} player has none", player),
+                        1 => format!("the {} player wins in column {}", player, columns[0]),
+                        _ => format!(
+                            "the {} player wins in columns {}",
+                            player,
+                            columns.join(", ")
+                        ),
+                    }
+                })
+                .collect();
+            Some(format!("Immediate threats: {}.", threats.join(", ")))
+        }
+        _ => None,
+    }
+}
+
+// Columns in which a token of the player would complete a line, whoever has to move
+fn winning_columns(game: &Connect4, player: Player) -> Vec<usize> {
+    (0..BOARD_WIDTH)
+        .filter(|&action| {
+            if !game.valid_action(action) {
+                return false;
+            }
+            let mut game = game.clone();
+            game.to_play = player;
+            game.play(action);
+            game.check_winner() == Some(player)
+        })
+        .collect()
+}
+
+// Best column and its score, if any column is not full
+fn best_column(scores: &[Option<i32>; BOARD_WIDTH]) -> Option<(i32, usize)> {
+    (0..BOARD_WIDTH)
+        .filter_map(|action| scores[action].map(|score| (score, action)))
+        .max()
+}
+
+fn describe_scores(scores: &[Option<i32>; BOARD_WIDTH], first_column: usize) -> String {
+    let columns: Vec<String> = scores
+        .iter()
+        .enumerate()
+        .map(|(action, score)| match score {
+            Some(score) => format!("{}: {}", action + first_column, ai::describe_score(*score)),
+            None => format!("{}: full", action + first_column),
+        })
+        .collect();
+    columns.join(", ")
+}
+
+// Print the position reached by the given moves, written as the numbers of their columns, with the
+// score of each column for the player to move, the best line and whether the position is solved
+pub fn analyse(moves: &str, depth: usize, appearance: Appearance) {
+    let first_column = appearance.first_column;
+    let last_column = first_column + BOARD_WIDTH - 1;
+    let mut game = Connect4::new();
+    let mut history = Vec::new();
+    for (index, character) in moves.chars().filter(|c| !c.is_whitespace()).enumerate() {
+        let action = character
+            .to_digit(10)
+            .map(|number| number as usize)
+            .filter(|number| (first_column..=last_column).contains(number))
+            .map(|number| number - first_column);
+        let error = match action {
+            _ if game.over() => format!("the game is over after {} moves", index),
+            Some(action) if game.valid_action(action) => {
+                game.play(action);
+                history.push(action as u8);
+                continue;
+            }
+            Some(_) => format!("column {} is full at move {}", character, index + 1),
+            None => format!(
+                "move {} is {}, the columns are numbered from {} to {}",
+                index + 1,
+                character,
+                first_column,
+                last_column
+            ),
+        };
+        println!("Unvalid moves: {}.", error);
+        return;
+    }
+    println!(
+        "\n{}\n",
+        board::render(&game, history.last().copied(), appearance)
+    );
+    if game.over() {
+        match game.check_winner() {
+            Some(winner) => println!("The {} player won the game.", winner),
+            None => println!("The game ended in a draw."),
+        }
+        return;
+    }
+    let player = game.to_play;
+    let scores = ai::column_scores(&game, depth);
+    let (score, best) = best_column(&scores).unwrap();
+    println!(
+        "After {} moves, the {} player is to move.\nScores of the columns (depth {}): {}.",
+        history.len(),
+        player,
+        depth,
+        describe_scores(&scores, first_column)
+    );
+    let line: Vec<String> = best_line(&game, best, depth)
+        .iter()
+        .map(|action| (action + first_column).to_string())
+        .collect();
+    println!("Best line: {}.", line.join(" "));
+    // The search is exhaustive when the board is full before reaching its depth
+    let empty_cells = game
+        .board
+        .iter()
+        .filter(|&&cell| cell == Cell::Empty)
+        .count();
+    match score {
+        ai::WIN => println!(
+            "Solved: the {} player wins with column {}.",
+            player,
+            best + first_column
+        ),
+        ai::LOSS => println!("Solved: the {} player loses whatever they play.", player),
+        _ if empty_cells <= depth => println!("Solved: the game is a draw with the best play."),
+        _ => println!(
+            "Not solved at depth {}: the best score of the {} player is {} with column {}.",
+            depth,
+            player,
+            ai::describe_score(score),
+            best + first_column
+        ),
+    }
+}
+
+// Best moves of both players after the best column of the position, each one searched at one ply
+// less than the previous one so that the scores agree
+fn best_line(game: &Connect4, best: usize, depth: usize) -> Vec<usize> {
+    let mut game = game.clone();
+    let mut line = vec![best];
+    game.play(best);
+    for depth in (1..depth).rev() {
+        if game.over() {
+            break;
+        }
+        let (_, action) = best_column(&ai::column_scores(&game, depth)).unwrap();
+        game.play(action);
+        line.push(action);
+    }
+    line
+}
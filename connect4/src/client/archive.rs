@@ -0,0 +1,74 @@
+use crate::{
+    archive::{self, GameRecord, Query, MAX_QUERY_RESULTS},
+    communication::{
+        Message::{self, *},
+        Termination,
+    },
+};
+use std::net::TcpStream;
+
+// Print the games of the archive of the server matching the query, with the columns numbered from
+// the given first column
+pub fn search_archive(socket_address: (String, u16), query: Query, first_column: usize) {
+    let mut server = TcpStream::connect(&socket_address).unwrap();
+    Search(query).send_to(&mut server);
+    let mut games = Vec::new();
+    loop {
+        match Message::receive_from(&mut server) {
+            ArchivedGame(game) => games.push(game),
+            EndOfSearch => break,
+            message => panic!("Unexpected message: {:?}", message),
+        }
+    }
+    if games.is_empty() {
+        println!("No archived game matches the search.");
+        return;
+    }
+    if games.len() == MAX_QUERY_RESULTS {
+        println!(
+            "Only the {} most recent games are shown.",
+            MAX_QUERY_RESULTS
+        );
+    }
+    for game in games {
+        print_game(&game, first_column);
+    }
+}
+
+fn print_game(game: &GameRecord, first_column: usize) {
+    let name = |player: &String| {
+        if player.is_empty() {
+            String::from("guest")
+        } else {
+            player.clone()
+        }
+    };
+    let result = match (game.winner, game.termination) {
+        (_, Termination::Adjourned) => String::from("adjourned"),
+        (Some(winner), _) => format!("{} won", winner),
+        (None, _) => String::from("draw"),
+    };
+    let termination = match game.termination {
+        Termination::Normal | Termination::Adjourned => "",
+        Termination::TimeForfeit => " on time",
+        Termination::Abandoned => " by abandonment",
+        Termination::Resignation => " by resignation",
+        Termination::Agreement => " by agreement",
+    };
+    let moves: String = game
+        .moves
+        .iter()
+        .map(|&action| (action as usize + first_column).to_string())
+        .collect();
+    println!(
+        "#{} {} (red) vs {} (yellow), {}{}, {} to {}\n    {}",
+        game.number,
+        name(&game.players.0),
+        name(&game.players.1),
+        result,
+        termination,
+        archive::format_time(game.start),
+        archive::format_time(game.end),
+        moves
+    );
+}
@@ -0,0 +1,88 @@
+use crate::game_logic::{Cell, Connect4, Player, BOARD_HEIGHT, BOARD_WIDTH};
+
+// Ways of drawing the tokens of the board
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Theme {
+    Ascii,      // X and O, without colors
+    Color,      // red and yellow discs
+    ColorBlind, // blue discs and orange squares, told apart by their shape as well
+    Mono,       // full and empty discs, without colors
+}
+
+// How the board is drawn, and whether it is drawn in the full-screen interface
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Appearance {
+    pub full_screen: bool,
+    pub theme: Theme,
+    pub first_column: usize, // number of the leftmost column as shown to the player, 0 or 1
+}
+
+impl Theme {
+    pub fn symbol(self, player: Player) -> char {
+        let symbols = match self {
+            Theme::Ascii => ('X', 'O'),
+            Theme::Color => ('●', '●'),
+            Theme::ColorBlind => ('●', '■'),
+            Theme::Mono => ('●', '○'),
+        };
+        player.select(symbols.0, symbols.1).0
+    }
+
+    // Color of the tokens of the player in the palette of 256 colors of the terminals, if any
+    pub fn color(self, player: Player) -> Option<u8> {
+        match self {
+            Theme::Ascii | Theme::Mono => None,
+            Theme::Color => Some(player.select(196, 226).0),
+            Theme::ColorBlind => Some(player.select(33, 208).0),
+        }
+    }
+}
+
+// Board drawn with the theme, with the last move between brackets and the tokens of the winning
+// lines between stars, or in reverse video when the theme has colors
+pub fn render(game: &Connect4, last_move: Option<u8>, appearance: Appearance) -> String {
+    let theme = appearance.theme;
+    let last_move = last_move.map(|column| {
+        let column = column as usize;
+        let row = (0..BOARD_HEIGHT)
+            .rev()
+            .find(|&row| game[(row, column)] != Cell::Empty)
+            .unwrap_or(0);
+        (row, column)
+    });
+    let winning = game.winning_lines().concat();
+    let mut text = String::new();
+    for row in (0..BOARD_HEIGHT).rev() {
+        for column in 0..BOARD_WIDTH {
+            let won = winning.contains(&(row, column));
+            let (left, right) = match (won, last_move == Some((row, column))) {
+                (_, true) => ('[', ']'),
+                (true, false) if theme.color(Player::Red).is_none() => ('*', '*'),
+                _ => ('|', '|'),
+            };
+            text.push(left);
+            let player = match game[(row, column)] {
+                Cell::Empty => None,
+                Cell::Red => Some(Player::Red),
+                Cell::Yellow => Some(Player::Yellow),
+            };
+            match player.map(|player| (theme.symbol(player), theme.color(player))) {
+                None => text.push(' '),
+                Some((symbol, None)) => text.push(symbol),
+                Some((symbol, Some(color))) => {
+                    let reverse = if won { ";7" } else { "" };
+                    text.push_str(&format!(
+                        "\x1b[1;38;5;{}{}m{}\x1b[0m",
+                        color, reverse, symbol
+                    ));
+                }
+            }
+            text.push(right);
+        }
+        text.push('\n');
+    }
+    for column in 0..BOARD_WIDTH {
+        text.push_str(&format!(" {} ", column + appearance.first_column));
+    }
+    text
+}
@@ -1,77 +1,672 @@
+use super::{
+    analysis,
+    board::Appearance,
+    command::{self, Command},
+    interface::{Interface, Plain},
+    tui::Tui,
+};
 use crate::{
-    communication::Message::{self, *},
-    game_logic::{self, Connect4, Player},
+    clock::TimeControl,
+    communication::{
+        transport::{Channel, Transport},
+        Message::{self, *},
+        Termination, MAX_PASSWORD_LENGTH,
+    },
+    game_logic::{Connect4, Player},
+};
+use ratatui::crossterm::event::{self, Event as TerminalEvent};
+use std::{
+    io,
+    net::TcpStream,
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Duration,
 };
-use std::{io, net::TcpStream};
 
-// Run the client
-pub fn run(socket_address: (String, u16), replay_file: Option<String>) {
-    let mut server = TcpStream::connect(socket_address).unwrap();
-    let color = match Message::receive_from(&mut server) {
-        Hello(color) => color,
+// Number of attempts to resume the game after losing the connection, one per second
+const RECONNECTION_ATTEMPTS: u32 = 30;
+
+// Interval between two updates of the full-screen interface, for the clocks
+const TICK: Duration = Duration::from_millis(100);
+
+// Everything the client has to react to. The messages of the server come with the number of the
+// connection they were received on, so that the ones of a lost connection are ignored. The events
+// of the terminal are only read by the full-screen interface, they are None after every TICK.
+enum Event {
+    Server(usize, io::Result<Message>),
+    Input(String),
+    Terminal(Option<TerminalEvent>),
+    Analysis(Connect4, String), // answer of the AI about the position
+}
+
+// Connection to the server, which can be replaced when resuming the game. Games of a server
+// running in the same process cannot be resumed, they have no address.
+struct Connection {
+    server: Box<dyn Transport>,
+    number: usize,
+    socket_address: Option<(String, u16)>,
+    token: u64,
+    events: Sender<Event>,
+    lost: bool,          // whether the connection was lost for good, or the player left
+    hints: bool, // whether the server lets the player ask for the help of the AI in this game
+    analysing: bool, // whether the AI searches a position for the player
+    draw_offered: bool, // whether the opponent offered a draw since the last move of the player
+    first_column: usize, // number of the leftmost column for the player
+    ui: Box<dyn Interface>,
+}
+
+// How to find an opponent on the server
+pub enum Matchmaking {
+    QuickMatch,
+    CreateRoom(String, bool), // name of the room and whether it is private
+    JoinRoom(String),         // invite code of the room
+    Bot(u8),                  // level of a bot of the server
+    Tournament,               // the tournament of the server, which requires an account
+}
+
+// Run the client, logging in to the account (or registering it if the flag is true) if given
+pub fn run(
+    socket_address: (String, u16),
+    matchmaking: Matchmaking,
+    account: Option<(String, bool)>,
+    replay_file: Option<String>,
+    appearance: Appearance,
+) {
+    let mut server = TcpStream::connect(&socket_address).unwrap();
+    if let Some((name, register)) = account {
+        if !log_in(&mut server, name, register) {
+            return;
+        }
+    }
+    let bot = matches!(matchmaking, Matchmaking::Bot(_));
+    match matchmaking {
+        Matchmaking::QuickMatch => Join.send_to(&mut server),
+        Matchmaking::CreateRoom(name, private) => {
+            CreateRoom(name, private).send_to(&mut server);
+            match Message::receive_from(&mut server) {
+                RoomCode(code) => println!(
+                    "Room created, your opponent can join it with --join {}. Waiting for an \
+                    opponent...",
+                    code
+                ),
+                message => panic!("Unexpected message: {:?}", message),
+            }
+        }
+        Matchmaking::JoinRoom(code) => JoinRoom(code).send_to(&mut server),
+        Matchmaking::Bot(level) => PlayBot(level).send_to(&mut server),
+        Matchmaking::Tournament => {
+            JoinTournament.send_to(&mut server);
+            match Message::receive_from(&mut server) {
+                Registered => (),
+                NotFound => {
+                    println!("The server has no tournament open to registrations.");
+                    return;
+                }
+                message => panic!("Unexpected message: {:?}", message),
+            }
+            let (connection, events) = connect(Box::new(server), Some(socket_address), appearance);
+            play_tournament(connection, events);
+            return;
+        }
+    }
+    let (color, time_control) = match Message::receive_from(&mut server) {
+        Hello(color, time_control) => (color, time_control),
+        NotFound if bot => {
+            println!("The server has no bot of this level.");
+            return;
+        }
+        NotFound => {
+            println!("There is no room waiting for an opponent with this code.");
+            return;
+        }
+        // The administrator of the server sends a notice to the players they turn away
+        Notice(text) => {
+            println!("{}", notice(&text));
+            return;
+        }
         message => panic!("Unexpected message: {:?}", message),
     };
-    let (game, game_history) = play_game(server, color);
-    if let Some(filename) = replay_file {
-        game.save(filename, game_history);
+    play(
+        Box::new(server),
+        Some(socket_address),
+        (color, time_control),
+        replay_file,
+        appearance,
+    );
+}
+
+// Run the client for a game of a server running in the same process
+pub fn run_local(mut server: Channel, replay_file: Option<String>, appearance: Appearance) {
+    match server.receive().unwrap() {
+        Hello(color, time_control) => play(
+            Box::new(server),
+            None,
+            (color, time_control),
+            replay_file,
+            appearance,
+        ),
+        message => panic!("Unexpected message: {:?}", message),
+    }
+}
+
+// Start forwarding what the user types and the messages of the server
+fn connect(
+    server: Box<dyn Transport>,
+    socket_address: Option<(String, u16)>,
+    appearance: Appearance,
+) -> (Connection, mpsc::Receiver<Event>) {
+    let (events, receiver) = mpsc::channel();
+    let ui: Box<dyn Interface> = if appearance.full_screen {
+        read_terminal(events.clone());
+        Box::new(Tui::new(appearance))
+    } else {
+        read_input(events.clone());
+        Box::new(Plain::new(appearance))
+    };
+    let connection = Connection {
+        server,
+        number: 0,
+        socket_address,
+        token: 0,
+        events,
+        lost: false,
+        hints: false,
+        analysing: false,
+        draw_offered: false,
+        first_column: appearance.first_column,
+        ui,
+    };
+    connection.listen();
+    (connection, receiver)
+}
+
+// Play the games of the session which starts with the given color and time control, and save them
+// if asked. The games after the first one are saved with their number in the name of the file.
+fn play(
+    server: Box<dyn Transport>,
+    socket_address: Option<(String, u16)>,
+    (color, time_control): (Player, TimeControl),
+    replay_file: Option<String>,
+    appearance: Appearance,
+) {
+    let (mut connection, events) = connect(server, socket_address, appearance);
+    let mut next_game = Some((color, time_control));
+    let mut number = 1;
+    while let Some((color, time_control)) = next_game {
+        let (game, game_history) = play_game(&mut connection, &events, color, time_control);
+        if let Some(filename) = &replay_file {
+            game.save(numbered(filename, number), game_history);
+        }
+        number += 1;
+        next_game = wait_next_game(&mut connection, &events);
+    }
+}
+
+fn numbered(filename: &str, number: u32) -> String {
+    match (number, filename.rsplit_once('.')) {
+        (1, _) => filename.to_owned(),
+        (_, Some((stem, extension))) => format!("{}-{}.{}", stem, number, extension),
+        (_, None) => format!("{}-{}", filename, number),
+    }
+}
+
+// Points of a series, given in half points
+fn format_points(half_points: u8) -> String {
+    (half_points as f64 / 2.0).to_string()
+}
+
+// Wait for what follows a result: the next game of the series, or a rematch if both players accept
+// it. Returns the color and time control of the next game, if any.
+fn wait_next_game(
+    connection: &mut Connection,
+    events: &mpsc::Receiver<Event>,
+) -> Option<(Player, TimeControl)> {
+    let mut offered = false;
+    while !connection.lost {
+        match connection.next_event(events) {
+            Event::Input(line) => match command::parse(&line, connection.first_column) {
+                Ok(Command::Yes) if offered => {
+                    connection.send(Rematch(true));
+                    offered = false;
+                    connection.ui.status("Waiting for your opponent...");
+                }
+                Ok(Command::No) if offered => {
+                    connection.send(Rematch(false));
+                    return None;
+                }
+                Ok(Command::Quit) => {
+                    if offered {
+                        connection.send(Rematch(false));
+                    }
+                    return None;
+                }
+                Ok(Command::Help) => connection
+                    .ui
+                    .status(&command::help(connection.first_column)),
+                _ if offered => connection.ui.status("Please answer y or n."),
+                _ => connection.ui.status("Please wait for the next game."),
+            },
+            Event::Server(number, _) if number != connection.number => (),
+            Event::Server(_, Ok(Score(score, opponent_score))) => {
+                // The score is only worth showing once more than one game was played
+                if score + opponent_score > 2 {
+                    connection.ui.status(&format!(
+                        "Score of the series: {} for you, {} for your opponent.",
+                        format_points(score),
+                        format_points(opponent_score)
+                    ));
+                }
+            }
+            Event::Server(_, Ok(OfferRematch)) => {
+                offered = true;
+                connection
+                    .ui
+                    .status("\nPlay a rematch with the colors swapped? (y/n)");
+            }
+            Event::Server(_, Ok(NoRematch)) => {
+                connection.ui.status("There will be no rematch.");
+                return None;
+            }
+            Event::Server(_, Ok(ChatFrom(_, text))) => connection.ui.chat(&text),
+            Event::Server(_, Ok(Notice(text))) => connection.ui.status(&notice(&text)),
+            Event::Server(_, Ok(Hello(color, time_control))) => {
+                connection.ui.status("\nThe next game starts.");
+                return Some((color, time_control));
+            }
+            Event::Server(_, Ok(message)) => panic!("Unexpected message: {:?}", message),
+            Event::Server(_, Err(_)) => return None,
+            Event::Analysis(..) => (),
+            Event::Terminal(_) => unreachable!(),
+        }
+    }
+    None
+}
+
+// Play the games of the tournament as the server starts them, until the final rank is known
+fn play_tournament(mut connection: Connection, events: mpsc::Receiver<Event>) {
+    connection
+        .ui
+        .status("Registered to the tournament, waiting for it to start...");
+    while !connection.lost {
+        match connection.next_event(&events) {
+            Event::Input(line) => match command::parse(&line, connection.first_column) {
+                Ok(Command::Quit) => return,
+                Ok(Command::Help) => connection
+                    .ui
+                    .status(&command::help(connection.first_column)),
+                _ => connection.ui.status("Please wait for your next game."),
+            },
+            Event::Server(_, Ok(Notice(text))) => connection.ui.status(&notice(&text)),
+            Event::Server(number, _) if number != connection.number => (),
+            Event::Server(_, Ok(Hello(color, time_control))) => {
+                play_game(&mut connection, &events, color, time_control);
+                connection.ui.status("\nWaiting for your next game...");
+            }
+            Event::Server(_, Ok(TournamentOver(rank, count))) => {
+                connection.ui.status(&format!(
+                    "\nThe tournament is over, you ranked {} out of {}.",
+                    rank, count
+                ));
+                return;
+            }
+            Event::Server(_, Ok(message)) => panic!("Unexpected message: {:?}", message),
+            Event::Server(_, Err(_)) => {
+                connection
+                    .ui
+                    .status("\nThe connection to the server was lost.");
+                return;
+            }
+            Event::Analysis(..) => (),
+            Event::Terminal(_) => unreachable!(),
+        }
+    }
+}
+
+// Ask for the password of the account and send it to the server, returns false if it was refused
+fn log_in(server: &mut TcpStream, name: String, register: bool) -> bool {
+    let password = loop {
+        println!("Password for {}:", name);
+        let mut password = String::new();
+        io::stdin().read_line(&mut password).unwrap();
+        let password = password.trim_end_matches(&['\r', '\n'][..]).to_owned();
+        if !password.is_empty() && password.len() <= MAX_PASSWORD_LENGTH {
+            break password;
+        }
+        println!(
+            "Passwords must contain between 1 and {} bytes.",
+            MAX_PASSWORD_LENGTH
+        );
+    };
+    Login(name.clone(), password, register).send_to(server);
+    match Message::receive_from(server) {
+        Rating(rating) => {
+            println!("Logged in as {} (rating: {}).", name, rating);
+            true
+        }
+        LoginFailed if register => {
+            println!("The name {} is already taken.", name);
+            false
+        }
+        LoginFailed => {
+            println!("Wrong name or password.");
+            false
+        }
+        Notice(text) => {
+            println!("{}", notice(&text));
+            false
+        }
+        message => panic!("Unexpected message: {:?}", message),
+    }
+}
+
+// Print the public rooms waiting for an opponent
+pub fn list_rooms(socket_address: (String, u16)) {
+    let mut server = TcpStream::connect(&socket_address).unwrap();
+    ListRooms.send_to(&mut server);
+    match Message::receive_from(&mut server) {
+        Rooms(rooms) if rooms.is_empty() => println!("No room is waiting for an opponent."),
+        Rooms(rooms) => {
+            println!("Rooms waiting for an opponent (join one with --join <code>):");
+            for (code, name) in rooms {
+                println!("  {}  {}", code, name);
+            }
+        }
+        message => panic!("Unexpected message: {:?}", message),
     }
 }
 
-// Send the game over message
-fn game_over(result: Message) {
+// Text of the result of the game
+fn result_text(result: Message) -> &'static str {
     match result {
-        Lose => println!("You lost the game."),
-        Draw => println!("The game ended in a draw."),
-        Win => println!("Congratulations, you won the game!"),
+        Lose(Termination::Normal, _) => "You lost the game.",
+        Lose(Termination::TimeForfeit, _) => "You ran out of time, you lost the game.",
+        Lose(Termination::Abandoned, _) => "You did not come back in time, you lost the game.",
+        Draw => "The game ended in a draw.",
+        Win(Termination::Normal, _) => "Congratulations, you won the game!",
+        Win(Termination::TimeForfeit, _) => "Your opponent ran out of time, you won the game!",
+        Win(Termination::Abandoned, _) => "Your opponent left the game, you won the game!",
+        Lose(Termination::Resignation, _) => "You resigned, you lost the game.",
+        Win(Termination::Resignation, _) => "Your opponent resigned, you won the game!",
+        GameOver(_, Termination::Adjourned, _) => "The game was stopped by the server.",
         _ => unreachable!(),
     }
 }
 
-// Get the input from the player in the client
-fn input_action(server: &mut TcpStream) {
-    loop {
-        println!("\nPlease input your move:");
-        let mut action = String::new();
-        io::stdin()
-            .read_line(&mut action)
-            .expect("Failed to read action.");
-        let action: u8 = match action.trim().parse() {
-            Ok(column) => column,
-            Err(_) => continue,
+fn notice(text: &str) -> String {
+    format!("Notice from the server: {}", text)
+}
+
+// Start a thread which forwards the lines typed by the user, so that the messages of the server
+// are displayed while waiting for them
+fn read_input(events: Sender<Event>) {
+    thread::spawn(move || loop {
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {
+                if events.send(Event::Input(line)).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+// Start a thread which forwards the events of the terminal, and None when nothing happened for a
+// TICK, for the full-screen interface
+fn read_terminal(events: Sender<Event>) {
+    thread::spawn(move || loop {
+        let event = match event::poll(TICK) {
+            Ok(true) => match event::read() {
+                Ok(event) => Some(event),
+                Err(_) => return,
+            },
+            Ok(false) => None,
+            Err(_) => return,
         };
-        Action(action).send_to(server);
-        break;
+        if events.send(Event::Terminal(event)).is_err() {
+            return;
+        }
+    });
+}
+
+impl Connection {
+    // Wait for the next message of the server or line typed by the user. The events of the
+    // terminal are handled by the interface, which turns some of them into lines.
+    fn next_event(&mut self, events: &mpsc::Receiver<Event>) -> Event {
+        loop {
+            match events.recv().unwrap() {
+                Event::Terminal(Some(event)) => {
+                    if let Some(line) = self.ui.handle(event) {
+                        return Event::Input(line);
+                    }
+                }
+                Event::Terminal(None) => self.ui.tick(),
+                event @ Event::Analysis(..) => {
+                    self.analysing = false;
+                    return event;
+                }
+                event => return event,
+            }
+        }
+    }
+
+    // Start a thread which searches the position for the player, so that the interface and the
+    // clocks keep running meanwhile
+    fn analyse(&mut self, command: Command, game: &Connect4) {
+        let (events, game, first_column) = (self.events.clone(), game.clone(), self.first_column);
+        self.analysing = true;
+        thread::spawn(move || {
+            if let Some(text) = analysis::answer(&command, &game, first_column) {
+                let _ = events.send(Event::Analysis(game, text));
+            }
+        });
+    }
+
+    // Start a thread which forwards the messages of the server
+    fn listen(&self) {
+        let number = self.number;
+        let events = self.events.clone();
+        let mut server = self.server.try_clone().unwrap();
+        thread::spawn(move || loop {
+            let message = server.receive();
+            let lost = message.is_err();
+            if events.send(Event::Server(number, message)).is_err() || lost {
+                return;
+            }
+        });
     }
+
+    // Send a message, a lost connection is noticed by the thread reading it
+    fn send(&mut self, message: Message) {
+        let _ = self.server.send(message);
+    }
+
+    // Try to resume the game after losing the connection, returns the moves played so far
+    fn reconnect(&mut self) -> Option<Vec<u8>> {
+        let socket_address = self.socket_address.as_ref()?;
+        self.ui
+            .status("\nThe connection to the server was lost, trying to resume the game...");
+        for _ in 0..RECONNECTION_ATTEMPTS {
+            thread::sleep(Duration::from_secs(1));
+            let mut server = match TcpStream::connect(socket_address) {
+                Ok(server) => server,
+                Err(_) => continue,
+            };
+            if Resume(self.token).write_to(&mut server).is_err() {
+                continue;
+            }
+            match Message::read_from(&mut server) {
+                Ok(Hello(..)) => (),
+                Ok(NotFound) => return None,
+                _ => continue,
+            }
+            if let Ok(History(history)) = Message::read_from(&mut server) {
+                self.server = Box::new(server);
+                self.number += 1;
+                self.listen();
+                return Some(history);
+            }
+        }
+        None
+    }
+}
+
+// Handle a line typed by the user during a game: a column to play if it is their turn, a chat
+// message, a request for the help of the AI or another command. Returns true if a move was sent.
+fn handle_input(line: &str, connection: &mut Connection, game: &Connect4, to_play: bool) -> bool {
+    let first_column = connection.first_column;
+    let command = match command::parse(line, first_column) {
+        Ok(command) => command,
+        Err(error) => {
+            connection.ui.status(&error);
+            return false;
+        }
+    };
+    match command {
+        Command::Play(_) if !to_play => connection
+            .ui
+            .status("It is not your turn, please wait for your opponent."),
+        Command::Play(action) if !game.valid_action(action) => connection.ui.status(&format!(
+            "Column {} is full, choose another one.",
+            action + first_column
+        )),
+        Command::Play(action) => {
+            connection.send(Action(action as u8));
+            connection.draw_offered = false;
+            return true;
+        }
+        Command::Chat(text) => connection.send(Chat(text)),
+        Command::Hint | Command::Eval | Command::Threats if !connection.hints => connection
+            .ui
+            .status("The server does not allow the help of the AI in this game."),
+        Command::Hint | Command::Eval if !to_play => connection
+            .ui
+            .status("The AI only gives hints and evaluations on your turn."),
+        Command::Hint | Command::Eval if connection.analysing => connection
+            .ui
+            .status("The AI is still searching, please wait for its answer."),
+        Command::Hint | Command::Eval => connection.analyse(command, game),
+        Command::Threats => {
+            if let Some(text) = analysis::answer(&command, game, first_column) {
+                connection.ui.status(&text);
+            }
+        }
+        Command::Resign => connection.send(Resign),
+        Command::Draw => {
+            connection.send(OfferDraw);
+            // Accepting the offer of the opponent ends the game right away
+            if !connection.draw_offered {
+                connection.ui.status(
+                    "You offered a draw, it stands until your opponent plays their next move.",
+                );
+            }
+        }
+        Command::Undo => connection
+            .ui
+            .status("Moves cannot be taken back in the games of a server."),
+        Command::Yes | Command::No => connection.ui.status("There is no offer to answer."),
+        Command::Help => connection.ui.status(&command::help(first_column)),
+        Command::Quit => {
+            connection.send(Resign);
+            connection.lost = true;
+        }
+    }
+    false
 }
 
 // Function for playing the game using the server
-fn play_game(mut server: TcpStream, color: Player) -> (Connect4, Vec<u8>) {
+fn play_game(
+    connection: &mut Connection,
+    events: &mpsc::Receiver<Event>,
+    color: Player,
+    time_control: TimeControl,
+) -> (Connect4, Vec<u8>) {
     let mut game = Connect4::new();
-    println!(
-        "You are playing with {} (symbol: {}).\n Columns are numbered from 0 to {} inclusive, \
-            starting from the left.\n\n{}\n\n",
-        color,
-        char::from(color),
-        game_logic::BOARD_WIDTH - 1,
-        game,
-    );
+    connection.hints = false;
+    connection.draw_offered = false;
+    connection.ui.start(color, time_control);
     let mut game_history = Vec::new();
+    let mut to_play = false;
+    let mut rating = None;
     loop {
-        match Message::receive_from(&mut server) {
-            Play => input_action(&mut server),
+        let message = match connection.next_event(events) {
+            Event::Input(line) => {
+                to_play &= !handle_input(line.trim(), connection, &game, to_play);
+                continue;
+            }
+            // The answer is only shown while the position it is about is on the board
+            Event::Analysis(position, text) => {
+                if position == game {
+                    connection.ui.status(&text);
+                }
+                continue;
+            }
+            Event::Server(number, _) if number != connection.number => continue,
+            Event::Server(_, Ok(message)) => message,
+            Event::Terminal(_) => unreachable!(),
+            Event::Server(_, Err(_)) => match connection.reconnect() {
+                Some(history) => {
+                    game = Connect4::from_moves(&history).unwrap();
+                    connection.ui.resumed(&game, &history);
+                    game_history = history;
+                    to_play = false;
+                    continue;
+                }
+                None => {
+                    connection.ui.status("\nUnable to resume the game.");
+                    connection.lost = true;
+                    return (game, game_history);
+                }
+            },
+        };
+        match message {
+            Token(token) => connection.token = token,
+            GameId(id) if connection.socket_address.is_some() => connection.ui.status(&format!(
+                "Game {} (spectators can watch it with --spectate {}).",
+                id, id
+            )),
+            GameId(_) => (),
+            Players(red, yellow) => connection.ui.players((red, yellow)),
+            Hints(allowed) => {
+                connection.hints = allowed;
+                if allowed {
+                    connection.ui.status(&format!(
+                        "The server allows the help of the AI in this game: {}.",
+                        analysis::COMMANDS
+                    ));
+                }
+            }
+            Play(clocks) => {
+                connection.ui.ask_move(clocks);
+                to_play = true;
+            }
             InvalidAction => {
-                println!("\nInvalid action.");
+                connection.ui.invalid_move();
+                to_play = true;
             }
+            ChatFrom(_, text) => connection.ui.chat(&text),
+            OfferDraw => {
+                connection.draw_offered = true;
+                connection
+                    .ui
+                    .status("Your opponent offers a draw, type draw to accept it.");
+            }
+            // The new rating of the player is sent just before the result of a rated game
+            Rating(new_rating) => rating = Some(new_rating),
             ValidAction(action) => {
                 game.play(action as usize);
                 game_history.push(action);
-                println!(
-                    "\n{}\n\nA token has been placed in column {}.\n",
-                    game, action
-                );
+                connection.ui.moved(&game, action);
             }
-            result @ (Lose | Draw | Win) => {
-                game_over(result);
+            Notice(text) => connection.ui.status(&notice(&text)),
+            result @ (Lose(..) | Draw | Win(..) | GameOver(..)) => {
+                connection.ui.game_over(result_text(result));
+                if let Some(rating) = rating {
+                    connection
+                        .ui
+                        .status(&format!("Your new rating: {}.", rating));
+                }
                 return (game, game_history);
             }
             message => panic!("Unexpected message: {:?}", message),
@@ -0,0 +1,98 @@
+use crate::{communication::MAX_CHAT_LENGTH, game_logic::BOARD_WIDTH};
+
+// Commands typed by the player, in games online or on this terminal
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    Play(usize), // index of the column, counted from 0 whatever the numbering shown to the player
+    Chat(String),
+    Hint,
+    Eval,
+    Threats,
+    Resign,
+    Draw, // offer a draw, or accept the one of the opponent
+    Undo, // take back the last move
+    Yes,
+    No,
+    Help,
+    Quit,
+}
+
+// List of the commands, with the columns numbered from the given first column
+pub fn help(first_column: usize) -> String {
+    format!(
+        "Commands:\n  \
+        <column>         place a token in the column, numbered from {} to {}\n  \
+        chat <message>   talk to your opponent\n  \
+        hint             ask the AI for the best column\n  \
+        eval             ask the AI for the score of each column\n  \
+        threats          show the columns in which a player would win right away\n  \
+        resign           give up the game\n  \
+        draw             offer a draw, or accept the one of your opponent\n  \
+        undo             take back your last move, in the games on this terminal\n  \
+        y, n             answer an offer of a rematch\n  \
+        help             show this message\n  \
+        quit             leave, resigning the game if one is running",
+        first_column,
+        first_column + BOARD_WIDTH - 1
+    )
+}
+
+// Parse a line typed by the player, with the columns numbered from the given first column.
+// Returns what is wrong with the line otherwise.
+pub fn parse(line: &str, first_column: usize) -> Result<Command, String> {
+    let line = line.trim();
+    let (word, argument) = line.split_once(' ').unwrap_or((line, ""));
+    let argument = argument.trim();
+    let word = word.to_lowercase();
+    if let Ok(number) = word.parse::<i64>() {
+        let last_column = first_column + BOARD_WIDTH - 1;
+        return match usize::try_from(number) {
+            Ok(column) if (first_column..=last_column).contains(&column) && argument.is_empty() => {
+                Ok(Command::Play(column - first_column))
+            }
+            _ if !argument.is_empty() => Err(String::from("Type a single column at a time.")),
+            _ => Err(format!(
+                "There is no column {}, the columns are numbered from {} to {}.",
+                number, first_column, last_column
+            )),
+        };
+    }
+    let command = match word.as_str() {
+        "" => {
+            return Err(String::from(
+                "Type the number of a column, or help for the commands.",
+            ))
+        }
+        "chat" | "say" if argument.is_empty() => {
+            return Err(String::from("Type your message after chat."));
+        }
+        "chat" | "say" if argument.len() > MAX_CHAT_LENGTH => {
+            return Err(format!(
+                "Chat messages must contain at most {} bytes.",
+                MAX_CHAT_LENGTH
+            ));
+        }
+        "chat" | "say" => return Ok(Command::Chat(argument.to_owned())),
+        "hint" => Command::Hint,
+        "eval" => Command::Eval,
+        "threats" => Command::Threats,
+        "resign" => Command::Resign,
+        "draw" => Command::Draw,
+        "undo" | "takeback" => Command::Undo,
+        "y" | "yes" => Command::Yes,
+        "n" | "no" => Command::No,
+        "help" | "h" | "?" => Command::Help,
+        "quit" | "exit" | "q" => Command::Quit,
+        _ => {
+            return Err(format!(
+                "Unknown command \"{}\", type help for the list of commands.",
+                word
+            ))
+        }
+    };
+    if argument.is_empty() {
+        Ok(command)
+    } else {
+        Err(format!("The command {} takes no argument.", word))
+    }
+}
@@ -0,0 +1,124 @@
+use crate::communication::{self, MAX_ACCOUNT_NAME_LENGTH};
+use serde::Deserialize;
+use std::{
+    env, fs,
+    io::ErrorKind,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+// Preferences of the player read from a TOML file. Every value is optional: the options of the
+// command line override the values of the file, which override the default values.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub player: PlayerConfig,
+    pub display: DisplayConfig,
+    pub ai: AiConfig,
+    pub files: FilesConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub ip: Option<String>,
+    pub port: Option<u16>,
+}
+
+// Account logged in to for the online games, unless the player asks to play as a guest
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerConfig {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub theme: Option<String>,
+    pub one_based: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AiConfig {
+    pub depth: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+    pub replays: Option<PathBuf>, // directory of the saved games
+}
+
+pub const THEMES: [&str; 4] = ["ascii", "color", "colorblind", "mono"];
+
+impl Config {
+    // Default location of the file, in the configuration directory of the user
+    pub fn default_path() -> Option<PathBuf> {
+        let directory = match env::var_os("XDG_CONFIG_HOME").filter(|path| !path.is_empty()) {
+            Some(directory) => PathBuf::from(directory),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(directory.join("connect4").join("client.toml"))
+    }
+
+    // Read and check a configuration file, returns all the errors found in it. A missing file is
+    // the same as an empty one.
+    pub fn load(path: &Path) -> Result<Self, Vec<String>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(vec![error.to_string()]),
+        };
+        let config: Self = toml::from_str(&content).map_err(|error| vec![error.to_string()])?;
+        let errors = config.errors();
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut check = |valid: bool, key: &str, expected: &str| {
+            if !valid {
+                errors.push(format!(
+                    "Unvalid value for {}. It should be {}.",
+                    key, expected
+                ));
+            }
+        };
+        check(
+            self.server
+                .ip
+                .as_ref()
+                .is_none_or(|ip| ip.parse::<IpAddr>().is_ok()),
+            "server.ip",
+            "an IP address",
+        );
+        check(
+            self.player
+                .name
+                .as_ref()
+                .is_none_or(|name| communication::valid_account_name(name)),
+            "player.name",
+            &format!(
+                "between 1 and {} letters, digits, '-' or '_'",
+                MAX_ACCOUNT_NAME_LENGTH
+            ),
+        );
+        check(
+            self.display
+                .theme
+                .as_ref()
+                .is_none_or(|theme| THEMES.contains(&theme.as_str())),
+            "display.theme",
+            "\"ascii\", \"color\", \"colorblind\" or \"mono\"",
+        );
+        check(self.ai.depth != Some(0), "ai.depth", "a positive integer");
+        errors
+    }
+}
@@ -0,0 +1,127 @@
+use super::board::{self, Appearance};
+use crate::{
+    clock::{self, TimeControl},
+    game_logic::{self, Connect4, Player},
+};
+use ratatui::crossterm::event::Event as TerminalEvent;
+use std::time::Duration;
+
+// How the games are shown to the player: lines printed on the standard output, or a full-screen
+// interface in the terminal
+pub trait Interface {
+    // Start of a game with the given color and time control
+    fn start(&mut self, color: Player, time_control: TimeControl);
+
+    // Names of the red and yellow players, empty for guests
+    fn players(&mut self, names: (String, String));
+
+    // A token was placed in the given column, the game is the one after the move
+    fn moved(&mut self, game: &Connect4, action: u8);
+
+    // The game was resumed after losing the connection, with the moves played so far
+    fn resumed(&mut self, game: &Connect4, history: &[u8]);
+
+    // The player has to move, with the remaining times of both players if the game is timed
+    fn ask_move(&mut self, clocks: Option<(Duration, Duration)>);
+
+    fn invalid_move(&mut self);
+
+    // Chat message of the opponent
+    fn chat(&mut self, text: &str);
+
+    fn game_over(&mut self, result: &str);
+
+    // Any other information: messages of the server, answers to what the player typed
+    fn status(&mut self, text: &str);
+
+    // Handle a key or another event of the terminal, returns the line it completes if any
+    fn handle(&mut self, _event: TerminalEvent) -> Option<String> {
+        None
+    }
+
+    // Called regularly, to update the clocks
+    fn tick(&mut self) {}
+}
+
+// Lines printed on the standard output, the moves are typed on the standard input
+pub struct Plain {
+    appearance: Appearance,
+    color: Option<Player>,
+}
+
+impl Plain {
+    pub fn new(appearance: Appearance) -> Self {
+        Self {
+            appearance,
+            color: None,
+        }
+    }
+}
+
+impl Interface for Plain {
+    fn start(&mut self, color: Player, time_control: TimeControl) {
+        self.color = Some(color);
+        println!(
+            "You are playing with {} (symbol: {}), with {}.\n Columns are numbered from {} to {} \
+            inclusive, starting from the left. Type \"chat\" followed by a message to talk to \
+            your opponent at any time, or help for the other commands.\n\n{}\n\n",
+            color,
+            self.appearance.theme.symbol(color),
+            time_control,
+            self.appearance.first_column,
+            self.appearance.first_column + game_logic::BOARD_WIDTH - 1,
+            board::render(&Connect4::new(), None, self.appearance),
+        );
+    }
+
+    fn players(&mut self, names: (String, String)) {
+        if let Some(color) = self.color {
+            let opponent = color.select(names.0, names.1).1;
+            if !opponent.is_empty() {
+                println!("Your opponent is {}.", opponent);
+            }
+        }
+    }
+
+    fn moved(&mut self, game: &Connect4, action: u8) {
+        println!(
+            "\n{}\n\nA token has been placed in column {}.\n",
+            board::render(game, Some(action), self.appearance),
+            action as usize + self.appearance.first_column
+        );
+    }
+
+    fn resumed(&mut self, game: &Connect4, history: &[u8]) {
+        println!(
+            "\nThe game has been resumed.\n\n{}\n",
+            board::render(game, history.last().copied(), self.appearance)
+        );
+    }
+
+    fn ask_move(&mut self, clocks: Option<(Duration, Duration)>) {
+        if let Some((remaining, other_remaining)) = clocks {
+            println!(
+                "\nTime left: {} for you, {} for your opponent.",
+                clock::format_duration(remaining),
+                clock::format_duration(other_remaining)
+            );
+        }
+        println!("\nPlease input your move:");
+    }
+
+    fn invalid_move(&mut self) {
+        println!("\nInvalid action.\n\nPlease input your move:");
+    }
+
+    fn chat(&mut self, text: &str) {
+        println!("Your opponent: {}", text);
+    }
+
+    fn game_over(&mut self, result: &str) {
+        println!("{}", result);
+    }
+
+    fn status(&mut self, text: &str) {
+        println!("{}", text);
+    }
+}
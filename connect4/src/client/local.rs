@@ -0,0 +1,184 @@
+use super::{
+    analysis,
+    board::{self, Appearance},
+    command::{self, Command},
+};
+use crate::{
+    ai,
+    game_logic::{Connect4, Player, BOARD_WIDTH},
+};
+use std::{
+    io, thread,
+    time::{Duration, Instant},
+};
+
+// Player of a game played without server
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Side {
+    Human,
+    Ai(usize), // depth of the search
+}
+
+// Play a game on this terminal between humans taking turns and the AI, without networking. The
+// moves of the AI take at least the given delay, so that they can be followed.
+pub fn play_local(
+    sides: (Side, Side),
+    delay: Duration,
+    appearance: Appearance,
+    replay_file: Option<String>,
+) {
+    let first_column = appearance.first_column;
+    let mut game = Connect4::new();
+    let mut game_history = Vec::new();
+    println!(
+        "Red ({}) plays with {} and yellow ({}) with {}.\n Columns are numbered from {} to {} \
+        inclusive, starting from the left. The AI can help with the commands {}, type help for \
+        the others.\n\n{}\n",
+        describe(sides.0),
+        appearance.theme.symbol(Player::Red),
+        describe(sides.1),
+        appearance.theme.symbol(Player::Yellow),
+        first_column,
+        first_column + BOARD_WIDTH - 1,
+        analysis::COMMANDS,
+        board::render(&game, None, appearance)
+    );
+    let humans = [sides.0, sides.1]
+        .iter()
+        .filter(|&&side| side == Side::Human)
+        .count();
+    // Player whose offer of a draw stands until their opponent moves
+    let mut draw_offer = None;
+    let result = loop {
+        if game.over() {
+            break match game.check_winner() {
+                Some(winner) => format!("The {} player won the game.", winner),
+                None => String::from("The game ended in a draw."),
+            };
+        }
+        let player = game.to_play;
+        let action = match player.select(sides.0, sides.1).0 {
+            Side::Human => {
+                match read_command(&game, (humans > 1).then_some(player), first_column) {
+                    Some(Command::Play(action)) => action,
+                    Some(Command::Resign) => {
+                        break format!(
+                            "The {} player resigned, the {} player won the game.",
+                            player,
+                            player.other()
+                        );
+                    }
+                    Some(Command::Draw) if draw_offer == Some(player.other()) => {
+                        break String::from("The players agreed to a draw.");
+                    }
+                    Some(Command::Draw) if humans > 1 => {
+                        draw_offer = Some(player);
+                        println!(
+                            "The {} player offers a draw, the {} player accepts it with draw.",
+                            player,
+                            player.other()
+                        );
+                        continue;
+                    }
+                    Some(Command::Draw) => {
+                        println!("The AI declines the draw.");
+                        continue;
+                    }
+                    Some(Command::Undo) => {
+                        if take_back(&mut game_history, sides) {
+                            game = Connect4::from_moves(&game_history).unwrap();
+                            draw_offer = None;
+                            println!(
+                                "\n{}\n\nThe last move was taken back.\n",
+                                board::render(&game, game_history.last().copied(), appearance)
+                            );
+                        } else {
+                            println!("There is no move to take back.");
+                        }
+                        continue;
+                    }
+                    // The moves played so far are still saved
+                    Some(Command::Quit) => break String::from("The game was abandoned."),
+                    Some(command) => panic!("Unexpected command: {:?}", command),
+                    None => return,
+                }
+            }
+            Side::Ai(depth) => {
+                let start = Instant::now();
+                let action = ai::action(game.clone(), depth);
+                thread::sleep(delay.saturating_sub(start.elapsed()));
+                action
+            }
+        };
+        // Moving declines the draw offered by the opponent
+        if draw_offer == Some(player.other()) {
+            draw_offer = None;
+        }
+        game.play(action);
+        game_history.push(action as u8);
+        println!(
+            "\n{}\n\nThe {} player placed a token in column {}.\n",
+            board::render(&game, Some(action as u8), appearance),
+            player,
+            action + first_column
+        );
+    };
+    println!("{}", result);
+    if let Some(filename) = replay_file {
+        game.save(filename, game_history);
+    }
+}
+
+fn describe(side: Side) -> String {
+    match side {
+        Side::Human => String::from("human"),
+        Side::Ai(depth) => format!("AI of depth {}", depth),
+    }
+}
+
+// Read commands on the standard input until one acts on the game, for the given player when
+// several humans take turns, answering the requests for the help of the AI meanwhile. Moves are
+// only returned if they are valid. Returns None if the input is closed.
+fn read_command(game: &Connect4, player: Option<Player>, first_column: usize) -> Option<Command> {
+    loop {
+        match player {
+            Some(player) => println!("\nPlease input the move of the {} player:", player),
+            None => println!("\nPlease input your move:"),
+        }
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        match command::parse(&line, first_column) {
+            Ok(Command::Play(action)) if !game.valid_action(action) => println!(
+                "Column {} is full, choose another one.",
+                action + first_column
+            ),
+            Ok(command @ (Command::Hint | Command::Eval | Command::Threats)) => {
+                if let Some(text) = analysis::answer(&command, game, first_column) {
+                    println!("{}", text);
+                }
+            }
+            Ok(Command::Help) => println!("{}", command::help(first_column)),
+            Ok(Command::Chat(_)) => println!("There is nobody to chat with in this game."),
+            Ok(Command::Yes | Command::No) => println!("There is no offer to answer."),
+            Ok(command) => return Some(command),
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+// Take back the last move of a human and the moves of the AI which followed it, so that it is
+// their turn again. Returns false if no human moved yet.
+fn take_back(history: &mut Vec<u8>, sides: (Side, Side)) -> bool {
+    match (0..history.len())
+        .rev()
+        .find(|&index| [sides.0, sides.1][index % 2] == Side::Human)
+    {
+        Some(index) => {
+            history.truncate(index);
+            true
+        }
+        None => false,
+    }
+}
@@ -1,12 +1,55 @@
 use clap::{Arg, ArgMatches, Command};
-use connect4::{ai, client};
+use connect4::{
+    ai,
+    archive::{self, Outcome, Query},
+    client::{self, Appearance, Config, Matchmaking, Side, Theme, THEMES},
+    communication::{self, MAX_ACCOUNT_NAME_LENGTH, MAX_ROOM_CODE_LENGTH, MAX_ROOM_NAME_LENGTH},
+    game_logic::BOARD_WIDTH,
+};
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+// What the client was asked to do
+enum Mode {
+    Play(Matchmaking, Option<(String, bool)>), // account to log in to, or to register if true
+    Ai(usize),                                 // depth of the search
+    Local((Side, Side), Duration),             // red and yellow, and delay of the moves of the AI
+    Spectate(u32),
+    ListRooms,
+    Search(Query),
+    Replay(String, usize, Option<usize>), // file, first ply and depth of the evaluation if shown
+    Analyse(String, usize),               // moves leading to the position and depth of the search
+    ShowConfig(String),                   // description of the effective settings
+}
 
 fn main() {
-    let (socket_address, save_replay, play_ai) = parse_args();
-    if let Some(depth) = play_ai {
-        ai::play_against(socket_address, depth, save_replay)
-    } else {
-        client::run(socket_address, save_replay);
+    let (socket_address, save_replay, appearance, mode) = parse_args();
+    match mode {
+        Mode::Play(matchmaking, account) => client::run(
+            socket_address,
+            matchmaking,
+            account,
+            save_replay,
+            appearance,
+        ),
+        Mode::Ai(depth) => ai::play_against(depth, save_replay, appearance),
+        Mode::Local(sides, delay) => client::play_local(sides, delay, appearance, save_replay),
+        Mode::Spectate(id) => client::spectate(socket_address, id, save_replay, appearance),
+        Mode::ListRooms => client::list_rooms(socket_address),
+        Mode::Search(query) => {
+            client::search_archive(socket_address, query, appearance.first_column)
+        }
+        Mode::Replay(filename, ply, eval) => {
+            let depth = eval.unwrap_or_else(|| ai::DEFAULT_DEPTH.parse().unwrap());
+            client::view_replay(&filename, ply, depth, eval.is_some(), appearance)
+        }
+        Mode::Analyse(moves, depth) => client::analyse(&moves, depth, appearance),
+        Mode::ShowConfig(settings) => println!("{}", settings),
     }
 }
 
@@ -21,10 +64,55 @@ fn cli() -> Command<'static> {
                 .long("ai")
                 .takes_value(false)
                 .help(
-                    "Play against an artificial intelligence on a local server. The first player \
-                    is chosen randomly. The evaluation algorithm does not take into account the \
-                    number of moves when it has found a winning strategy so it can miss a win in
-                    one move because it has found another (longer) winning strategy.",
+                    "Play against an artificial intelligence in the same process, without \
+                    networking. The first player is chosen randomly. The evaluation algorithm \
+                    does not take into account the number of moves when it has found a winning \
+                    strategy so it can miss a win in one move because it has found another \
+                    (longer) winning strategy.",
+                ),
+        )
+        .arg(
+            Arg::new("bot")
+                .short('b')
+                .long("bot")
+                .takes_value(true)
+                .conflicts_with_all(&["ai", "depth", "createroom", "join", "listrooms", "spectate"])
+                .help(
+                    "Play against a bot of the server of the given level, starting from 1. The \
+                    levels available depend on the server.",
+                ),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .possible_values(["red", "yellow"])
+                .requires("mode")
+                .help(
+                    "Color of the human player in the human-vs-ai mode, chosen randomly by \
+                    default. Red plays first.",
+                ),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .takes_value(true)
+                .global(true)
+                .help(
+                    "Configuration file of the client, by default connect4/client.toml in \
+                    $XDG_CONFIG_HOME or ~/.config. The options given on the command line \
+                    override its values.",
+                ),
+        )
+        .arg(
+            Arg::new("createroom")
+                .short('c')
+                .long("create-room")
+                .takes_value(true)
+                .min_values(0)
+                .conflicts_with_all(&["ai", "depth", "join", "listrooms", "spectate"])
+                .help(
+                    "Create a room on the server, optionally with a name, and wait in it for an \
+                    opponent. The invite code of the room is printed once it is created.",
                 ),
         )
         .arg(
@@ -39,28 +127,151 @@ fn cli() -> Command<'static> {
                     values).",
                 ),
         )
+        .arg(
+            Arg::new("delay")
+                .long("delay")
+                .default_value("0")
+                .requires("mode")
+                .help(
+                    "Minimum time taken by each move of the AI in a local mode, in seconds, to \
+                    follow an ai-vs-ai game.",
+                ),
+        )
+        .arg(
+            Arg::new("guest")
+                .long("guest")
+                .takes_value(false)
+                .conflicts_with_all(&["login", "register"])
+                .help("Play as a guest, even if the configuration file gives a player name"),
+        )
         .arg(
             Arg::new("ipaddress")
                 .short('i')
                 .long("ip")
                 .default_value("127.0.0.1")
+                .global(true)
                 .help("IP address of the server"),
         )
+        .arg(
+            Arg::new("join")
+                .short('j')
+                .long("join")
+                .takes_value(true)
+                .conflicts_with_all(&["ai", "depth", "listrooms", "spectate"])
+                .help("Join the room with the given invite code"),
+        )
+        .arg(
+            Arg::new("listrooms")
+                .short('l')
+                .long("list-rooms")
+                .takes_value(false)
+                .conflicts_with_all(&["ai", "depth", "spectate"])
+                .help("List the public rooms of the server waiting for an opponent"),
+        )
+        .arg(
+            Arg::new("login")
+                .long("login")
+                .takes_value(true)
+                .conflicts_with_all(&["ai", "depth", "listrooms", "register", "spectate"])
+                .help(
+                    "Log in to the account with the given name before looking for an opponent. \
+                    The password is asked at startup. Games between two players who logged in \
+                    are rated.",
+                ),
+        )
+        .arg(
+            Arg::new("mode")
+                .long("mode")
+                .possible_values(["hotseat", "human-vs-ai", "ai-vs-ai"])
+                .conflicts_with_all(&[
+                    "ai",
+                    "bot",
+                    "createroom",
+                    "join",
+                    "listrooms",
+                    "login",
+                    "register",
+                    "spectate",
+                    "tournament",
+                ])
+                .help(
+                    "Play a game on this terminal without server: two humans taking turns, a \
+                    human against the AI or the AI against itself. The depth of the search of \
+                    the AI is given by --depth, or for each color by --red-depth and \
+                    --yellow-depth.",
+                ),
+        )
+        .arg(Arg::new("plain").long("plain").takes_value(false).help(
+            "Print the games as lines of text instead of showing them in the full-screen \
+            interface. The plain mode is always used when the client does not run in a terminal.",
+        ))
         .arg(
             Arg::new("port")
                 .short('p')
                 .long("port")
                 .default_value("50001")
+                .global(true)
                 .help("Port on which the server is listening"),
         )
+        .arg(
+            Arg::new("private")
+                .long("private")
+                .takes_value(false)
+                .requires("createroom")
+                .help(
+                    "Do not list the created room, only the players who were given its invite \
+                    code can join it.",
+                ),
+        )
+        .arg(
+            Arg::new("reddepth")
+                .long("red-depth")
+                .takes_value(true)
+                .requires("mode")
+                .help("Depth of the search of the AI playing red in a local mode"),
+        )
+        .arg(
+            Arg::new("register")
+                .long("register")
+                .takes_value(true)
+                .conflicts_with_all(&["ai", "depth", "listrooms", "spectate"])
+                .help(
+                    "Create an account with the given name and log in to it. Names contain \
+                    letters, digits, '-' and '_'.",
+                ),
+        )
+        .arg(
+            Arg::new("replaydir")
+                .long("replay-dir")
+                .default_value("games")
+                .help("Directory in which the replay files are saved"),
+        )
         .arg(
             Arg::new("replayfile")
                 .short('r')
                 .long("replay")
                 .default_value("game.txt")
                 .help(
-                    "Save the game in a replay file in the \"games\" directory. If this argument \
-                    is specified, adding the flag savereplay will not have any effect.",
+                    "Save the game in a replay file in the directory of the replays. If this \
+                    argument is specified, adding the flag savereplay will not have any effect.",
+                ),
+        )
+        .arg(
+            Arg::new("zerobased")
+                .long("zero-based")
+                .takes_value(false)
+                .global(true)
+                .conflicts_with("onebased")
+                .help("Number the columns from 0, even if the configuration file says otherwise"),
+        )
+        .arg(
+            Arg::new("onebased")
+                .long("one-based")
+                .takes_value(false)
+                .global(true)
+                .help(
+                    "Number the columns from 1 instead of 0, on the board and in the commands \
+                    typed during the games.",
                 ),
         )
         .arg(
@@ -73,9 +284,131 @@ fn cli() -> Command<'static> {
                     saved in the default file.",
                 ),
         )
+        .arg(
+            Arg::new("spectate")
+                .long("spectate")
+                .takes_value(true)
+                .conflicts_with_all(&["ai", "depth"])
+                .help(
+                    "Watch the game with the given id on the server instead of playing. The id \
+                    of a game is given to both players when it starts.",
+                ),
+        )
+        .arg(
+            Arg::new("theme")
+                .long("theme")
+                .possible_values(THEMES)
+                .global(true)
+                .help(
+                    "How the tokens are drawn: X and O, red and yellow discs, blue discs and \
+                    orange squares, or full and empty discs without colors. The last move and \
+                    the winning fours are highlighted. Colors are used by default when the output \
+                    is a terminal.",
+                ),
+        )
+        .arg(
+            Arg::new("tournament")
+                .long("tournament")
+                .takes_value(false)
+                .conflicts_with_all(&["ai", "bot", "createroom", "depth", "join", "spectate"])
+                .help(
+                    "Take part in the tournament of the server, with the account given by --login \
+                    or --register. Its games are played one after another until the final \
+                    standings are known.",
+                ),
+        )
+        .arg(
+            Arg::new("yellowdepth")
+                .long("yellow-depth")
+                .takes_value(true)
+                .requires("mode")
+                .help("Depth of the search of the AI playing yellow in a local mode"),
+        )
+        .subcommand(
+            Command::new("archive")
+                .about("Search the games archived by the server, the most recent first")
+                .arg(
+                    Arg::new("player")
+                        .long("player")
+                        .takes_value(true)
+                        .help("Account of one of the players"),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .takes_value(true)
+                        .help("Games which ended on this day (YYYY-MM-DD, UTC) or later"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .takes_value(true)
+                        .help("Games which ended on this day (YYYY-MM-DD, UTC) or earlier"),
+                )
+                .arg(
+                    Arg::new("result")
+                        .long("result")
+                        .possible_values(["red", "yellow", "draw"])
+                        .help("Games won by red, won by yellow or drawn"),
+                )
+                .arg(
+                    Arg::new("opening")
+                        .long("opening")
+                        .takes_value(true)
+                        .help("First moves of the games, as column numbers (e.g. 3342)"),
+                ),
+        )
+        .subcommand(
+            Command::new("replay")
+                .about("Step through a saved game, move by move")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .help("Replay file, e.g. games/game.txt"),
+                )
+                .arg(
+                    Arg::new("eval")
+                        .long("eval")
+                        .takes_value(true)
+                        .min_values(0)
+                        .help(
+                            "Show the evaluation of the AI and its best move at each ply, \
+                            optionally with the depth of its search. It can also be toggled with \
+                            the eval command.",
+                        ),
+                )
+                .arg(
+                    Arg::new("ply")
+                        .long("ply")
+                        .default_value("0")
+                        .help("Number of moves played when the replay starts"),
+                ),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Show the settings in effect, from the configuration file and the options"),
+        )
+        .subcommand(
+            Command::new("analyse")
+                .alias("analyze")
+                .about("Evaluate a position with the AI")
+                .arg(Arg::new("moves").required(true).help(
+                    "Moves leading to the position, as the numbers of their columns (e.g. \
+                            4453), or \"\" for the empty board",
+                ))
+                .arg(
+                    Arg::new("depth")
+                        .short('d')
+                        .long("depth")
+                        .default_value(ai::DEFAULT_DEPTH)
+                        .help("Depth of the search of the AI"),
+                ),
+        )
         .after_help(
-            "Play either locally against an AI or online against someone else. The state of the \
-            game is maintained on the server side, not on the client side.",
+            "Play either locally against an AI or online against someone else. Online, the \
+            opponent is either the next player looking for a game, a player joining a room with \
+            its invite code or a bot of the server. The state of the game is maintained on the \
+            server side, not on the client side.",
         )
 }
 
@@ -87,23 +420,294 @@ fn optional_arg(arg_matches: &ArgMatches, arg: &str, flag: &str) -> Option<Strin
     }
 }
 
-fn parse_args() -> ((String, u16), Option<String>, Option<usize>) {
-    let app = cli();
-    let arg_matches = app.get_matches();
-    let ip = arg_matches.value_of("ipaddress").unwrap().trim().to_owned();
-    let port: u16 = arg_matches
-        .value_of("port")
-        .unwrap()
+// Same as the value of the argument, unless it was not given on the command line and the
+// configuration file has a value for it
+fn setting<T>(
+    arg_matches: &ArgMatches,
+    arg: &str,
+    file: Option<T>,
+    parse: impl Fn(&str) -> T,
+) -> T {
+    match (arg_matches.occurrences_of(arg), file) {
+        (0, Some(value)) => value,
+        _ => parse(arg_matches.value_of(arg).unwrap()),
+    }
+}
+
+// Read the configuration file, exits with all the errors found in it if it is not valid
+fn load_config(path: &Path) -> Config {
+    Config::load(path).unwrap_or_else(|errors| {
+        eprintln!("Unvalid configuration file {}:", path.display());
+        for error in errors {
+            eprintln!("  {}", error);
+        }
+        process::exit(1);
+    })
+}
+
+fn parse_depth(depth: &str) -> usize {
+    depth
         .trim()
         .parse()
-        .expect("Unvalid value for port. It should be an integer between 0 and 65,535.");
+        .ok()
+        .filter(|&depth| depth > 0)
+        .expect("Unvalid value for depth. It should be an positive integer.")
+}
+
+fn parse_args() -> ((String, u16), Option<String>, Appearance, Mode) {
+    let app = cli();
+    let arg_matches = app.get_matches();
+    let config_path = arg_matches
+        .value_of("config")
+        .map(PathBuf::from)
+        .or_else(Config::default_path);
+    let Config {
+        server,
+        player,
+        display,
+        ai: ai_config,
+        files,
+    } = config_path.as_deref().map(load_config).unwrap_or_default();
+    let ip = setting(&arg_matches, "ipaddress", server.ip, |ip| {
+        ip.trim().to_owned()
+    });
+    let port = setting(&arg_matches, "port", server.port, |port| {
+        port.trim()
+            .parse()
+            .expect("Unvalid value for port. It should be an integer between 0 and 65,535.")
+    });
     let socket_address = (ip, port);
-    let save_replay = optional_arg(&arg_matches, "replayfile", "savereplay")
-        .map(|filename| format!("games/{}", filename));
-    let play_ai = optional_arg(&arg_matches, "depth", "ai").map(|depth| {
-        depth
+    let depth = setting(&arg_matches, "depth", ai_config.depth, parse_depth);
+    let replay_directory = setting(&arg_matches, "replaydir", files.replays, |directory| {
+        PathBuf::from(directory)
+    });
+    let save_replay = optional_arg(&arg_matches, "replayfile", "savereplay").map(|filename| {
+        fs::create_dir_all(&replay_directory)
+            .expect("Unable to create the directory of the replays.");
+        replay_directory.join(filename).display().to_string()
+    });
+    // The full-screen interface needs a terminal to read the keys from and to draw in
+    let full_screen =
+        !arg_matches.is_present("plain") && io::stdin().is_terminal() && io::stdout().is_terminal();
+    let theme_name = match arg_matches.value_of("theme").or(display.theme.as_deref()) {
+        Some(theme) => theme,
+        None if io::stdout().is_terminal() => "color",
+        None => "ascii",
+    };
+    let theme = match theme_name {
+        "ascii" => Theme::Ascii,
+        "colorblind" => Theme::ColorBlind,
+        "mono" => Theme::Mono,
+        _ => Theme::Color,
+    };
+    let one_based = arg_matches.is_present("onebased")
+        || (!arg_matches.is_present("zerobased") && display.one_based == Some(true));
+    let appearance = Appearance {
+        full_screen,
+        theme,
+        first_column: if one_based { 1 } else { 0 },
+    };
+    // The account of the configuration file is only logged in to when no other is given
+    let account = match parse_account(&arg_matches) {
+        None if !arg_matches.is_present("guest") => player.name.map(|name| (name, false)),
+        account => account,
+    };
+    let mode = if let Some(arg_matches) = arg_matches.subcommand_matches("archive") {
+        Mode::Search(parse_query(arg_matches, appearance.first_column))
+    } else if let Some(arg_matches) = arg_matches.subcommand_matches("replay") {
+        let ply = arg_matches
+            .value_of("ply")
+            .unwrap()
+            .trim()
             .parse()
-            .expect("Unvalid value for depth. It should be an positive integer.")
+            .expect("Unvalid value for ply. It should be a positive integer.");
+        let eval = arg_matches
+            .is_present("eval")
+            .then(|| arg_matches.value_of("eval").map_or(depth, parse_depth));
+        Mode::Replay(arg_matches.value_of("file").unwrap().to_owned(), ply, eval)
+    } else if let Some(arg_matches) = arg_matches.subcommand_matches("analyse") {
+        let depth = setting(arg_matches, "depth", ai_config.depth, parse_depth);
+        Mode::Analyse(arg_matches.value_of("moves").unwrap().to_owned(), depth)
+    } else if arg_matches.subcommand_matches("config").is_some() {
+        let file = match &config_path {
+            Some(path) if path.exists() => path.display().to_string(),
+            Some(path) => format!("{} (not found)", path.display()),
+            None => String::from("none, the home directory is unknown"),
+        };
+        let player = match &account {
+            Some((name, _)) => name.as_str(),
+            None => "guest",
+        };
+        Mode::ShowConfig(format!(
+            "Configuration file: {}\nServer: {}:{}\nPlayer: {}\nTheme: {}\nColumns numbered \
+            from: {}\nDepth of the AI: {}\nReplay directory: {}",
+            file,
+            socket_address.0,
+            socket_address.1,
+            player,
+            theme_name,
+            appearance.first_column,
+            depth,
+            replay_directory.display()
+        ))
+    } else if let Some(id) = arg_matches.value_of("spectate") {
+        Mode::Spectate(
+            id.trim()
+                .parse()
+                .expect("Unvalid value for a game id. It should be a positive integer."),
+        )
+    } else if let Some(mode) = arg_matches.value_of("mode") {
+        let delay = arg_matches.value_of("delay").unwrap().trim();
+        let delay = delay
+            .parse()
+            .ok()
+            .and_then(|delay| Duration::try_from_secs_f64(delay).ok())
+            .expect("Unvalid value for delay. It should be a positive number of seconds.");
+        Mode::Local(parse_sides(&arg_matches, mode, depth), delay)
+    } else if arg_matches.is_present("ai") || arg_matches.occurrences_of("depth") > 0 {
+        Mode::Ai(depth)
+    } else if arg_matches.is_present("listrooms") {
+        Mode::ListRooms
+    } else {
+        let matchmaking = parse_matchmaking(&arg_matches, account.is_some());
+        Mode::Play(matchmaking, account)
+    };
+    (socket_address, save_replay, appearance, mode)
+}
+
+// Red and yellow players of a local game
+fn parse_sides(arg_matches: &ArgMatches, mode: &str, depth: usize) -> (Side, Side) {
+    let depth = |arg| Side::Ai(arg_matches.value_of(arg).map_or(depth, parse_depth));
+    let ai = (depth("reddepth"), depth("yellowdepth"));
+    match mode {
+        "hotseat" => (Side::Human, Side::Human),
+        "ai-vs-ai" => ai,
+        _ => {
+            let red = match arg_matches.value_of("color") {
+                Some(color) => color == "red",
+                None => rand::random(),
+            };
+            if red {
+                (Side::Human, ai.1)
+            } else {
+                (ai.0, Side::Human)
+            }
+        }
+    }
+}
+
+// How to find an opponent, the tournament requires to log in to an account
+fn parse_matchmaking(arg_matches: &ArgMatches, account: bool) -> Matchmaking {
+    if arg_matches.is_present("createroom") {
+        let name = arg_matches
+            .value_of("createroom")
+            .unwrap_or("Connect4")
+            .trim()
+            .to_owned();
+        assert!(
+            !name.is_empty() && name.len() <= MAX_ROOM_NAME_LENGTH,
+            "Unvalid room name. It should contain between 1 and {} bytes.",
+            MAX_ROOM_NAME_LENGTH
+        );
+        Matchmaking::CreateRoom(name, arg_matches.is_present("private"))
+    } else if arg_matches.is_present("tournament") {
+        assert!(
+            account,
+            "Taking part in a tournament requires an account, given by --login, --register or \
+            the configuration file."
+        );
+        Matchmaking::Tournament
+    } else if let Some(level) = arg_matches.value_of("bot") {
+        Matchmaking::Bot(
+            level
+                .trim()
+                .parse()
+                .expect("Unvalid level. It should be an integer between 1 and 255."),
+        )
+    } else if let Some(code) = arg_matches.value_of("join") {
+        let code = code.trim().to_uppercase();
+        assert!(
+            !code.is_empty()
+                && code.len() <= MAX_ROOM_CODE_LENGTH
+                && code.bytes().all(|byte| byte.is_ascii_alphanumeric()),
+            "Unvalid invite code. It should contain between 1 and {} letters and digits.",
+            MAX_ROOM_CODE_LENGTH
+        );
+        Matchmaking::JoinRoom(code)
+    } else {
+        Matchmaking::QuickMatch
+    }
+}
+
+// Name of the account to log in to, and whether it has to be registered
+fn parse_account(arg_matches: &ArgMatches) -> Option<(String, bool)> {
+    let (name, register) = match (
+        arg_matches.value_of("login"),
+        arg_matches.value_of("register"),
+    ) {
+        (Some(name), _) => (name, false),
+        (_, Some(name)) => (name, true),
+        _ => return None,
+    };
+    let name = name.trim().to_owned();
+    assert!(
+        communication::valid_account_name(&name),
+        "Unvalid account name. It should contain between 1 and {} letters, digits, '-' or '_'.",
+        MAX_ACCOUNT_NAME_LENGTH
+    );
+    Some((name, register))
+}
+
+// Search of the archive, with the columns of the opening numbered from the given first column
+fn parse_query(arg_matches: &ArgMatches, first_column: usize) -> Query {
+    let date = |arg| {
+        arg_matches.value_of(arg).map(|date| {
+            archive::parse_date(date)
+                .expect("Unvalid date. It should be given as YYYY-MM-DD, e.g. 2022-06-01.")
+        })
+    };
+    let outcome = arg_matches.value_of("result").map(|result| match result {
+        "red" => Outcome::RedWins,
+        "yellow" => Outcome::YellowWins,
+        _ => Outcome::Draw,
+    });
+    let opening = arg_matches
+        .value_of("opening")
+        .map_or(Vec::new(), |opening| {
+            opening
+                .trim()
+                .chars()
+                .map(
+                    |action| match action.to_digit(10).map(|action| action as usize) {
+                        Some(action)
+                            if (first_column..first_column + BOARD_WIDTH).contains(&action) =>
+                        {
+                            (action - first_column) as u8
+                        }
+                        _ => panic!(
+                            "Unvalid opening. It should only contain columns between {} and {}.",
+                            first_column,
+                            first_column + BOARD_WIDTH - 1
+                        ),
+                    },
+                )
+                .collect()
+        });
+    let player = arg_matches.value_of("player").map(|player| {
+        let player = player.trim().to_owned();
+        assert!(
+            communication::valid_account_name(&player),
+            "Unvalid account name. It should contain between 1 and {} letters, digits, '-' or '_'.",
+            MAX_ACCOUNT_NAME_LENGTH
+        );
+        player
     });
-    (socket_address, save_replay, play_ai)
+    Query {
+        player,
+        from: date("from"),
+        // The last day is included
+        to: date("to").map(|to| to + 86_400),
+        outcome,
+        opening,
+    }
 }
@@ -1,3 +1,20 @@
+mod analysis;
+mod archive;
+mod board;
 mod client;
+mod command;
+mod config;
+mod interface;
+mod local;
+mod replay;
+mod spectator;
+mod tui;
 
-pub use client::run;
+pub use analysis::analyse;
+pub use archive::search_archive;
+pub use board::{Appearance, Theme};
+pub use client::{list_rooms, run, run_local, Matchmaking};
+pub use config::{Config, THEMES};
+pub use local::{play_local, Side};
+pub use replay::view_replay;
+pub use spectator::spectate;
@@ -0,0 +1,108 @@
+use super::board::{self, Appearance};
+use crate::{
+    ai,
+    game_logic::{Connect4, BOARD_WIDTH},
+};
+use std::io;
+
+const COMMANDS: &str = "Commands: Enter or n (next move), p (previous move), the number of a ply, \
+    s (start), e (end), eval (show or hide the evaluation), q (quit).";
+
+// Step through a saved game, from the given ply. The evaluation of the AI, searching at the given
+// depth, is shown at each ply if asked.
+pub fn view_replay(
+    filename: &str,
+    start: usize,
+    eval_depth: usize,
+    eval: bool,
+    appearance: Appearance,
+) {
+    let history = match Connect4::load_moves(filename) {
+        Ok(history) => history,
+        Err(error) => {
+            println!("Unable to read the replay {}: {}", filename, error);
+            return;
+        }
+    };
+    let end = Connect4::from_moves(&history).unwrap();
+    let result = match end.check_winner() {
+        Some(winner) => format!("the {} player won", winner),
+        None if end.over() => String::from("draw"),
+        None => String::from("not finished"),
+    };
+    println!(
+        "Replay of {}: {} moves, {}.\n{}",
+        filename,
+        history.len(),
+        result,
+        COMMANDS
+    );
+    let mut ply = start.min(history.len());
+    let mut eval = eval;
+    loop {
+        show(&history, ply, appearance, eval.then_some(eval_depth));
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        match line.trim() {
+            "" | "n" | "next" if ply < history.len() => ply += 1,
+            "" | "n" | "next" => println!("This is the end of the game."),
+            "p" | "prev" | "previous" if ply > 0 => ply -= 1,
+            "p" | "prev" | "previous" => println!("This is the start of the game."),
+            "s" | "start" => ply = 0,
+            "e" | "end" => ply = history.len(),
+            "eval" => eval = !eval,
+            "q" | "quit" => return,
+            command => match command.parse::<usize>() {
+                Ok(number) if number <= history.len() => ply = number,
+                _ => println!(
+                    "Unknown command, plies are numbered from 0 to {}.\n{}",
+                    history.len(),
+                    COMMANDS
+                ),
+            },
+        }
+    }
+}
+
+// Board after the given number of moves, with the evaluation of the position if a depth is given
+fn show(history: &[u8], ply: usize, appearance: Appearance, eval_depth: Option<usize>) {
+    let game = Connect4::from_moves(&history[..ply]).unwrap();
+    let last_move = ply.checked_sub(1).map(|index| history[index]);
+    let first_column = appearance.first_column;
+    println!("\n{}\n", board::render(&game, last_move, appearance));
+    match last_move {
+        Some(action) => println!(
+            "Ply {}/{}: the {} player placed a token in column {}.",
+            ply,
+            history.len(),
+            game.to_play.other(),
+            action as usize + first_column
+        ),
+        None => println!("Ply 0/{}: start of the game.", history.len()),
+    }
+    if let (Some(depth), false) = (eval_depth, game.over()) {
+        let scores = ai::column_scores(&game, depth);
+        let (score, best) = (0..BOARD_WIDTH)
+            .filter_map(|action| scores[action].map(|score| (score, action)))
+            .max()
+            .unwrap();
+        let played = history.get(ply).map_or(String::new(), |&action| {
+            let score = scores[action as usize].unwrap();
+            format!(
+                ", {} with the move played (column {})",
+                ai::describe_score(score),
+                action as usize + first_column
+            )
+        });
+        println!(
+            "Evaluation for the {} player (depth {}): {} with column {}{}.",
+            game.to_play,
+            depth,
+            ai::describe_score(score),
+            best + first_column,
+            played
+        );
+    }
+}
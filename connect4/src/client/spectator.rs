@@ -0,0 +1,88 @@
+use super::board::{self, Appearance};
+use crate::{
+    communication::{
+        Message::{self, *},
+        Termination,
+    },
+    game_logic::{Connect4, Player},
+};
+use std::net::TcpStream;
+
+// Watch a game hosted by the server
+pub fn spectate(
+    socket_address: (String, u16),
+    id: u32,
+    replay_file: Option<String>,
+    appearance: Appearance,
+) {
+    let theme = appearance.theme;
+    let mut server = TcpStream::connect(socket_address).unwrap();
+    Spectate(id).send_to(&mut server);
+    let mut game_history = match Message::receive_from(&mut server) {
+        History(history) => history,
+        NotFound => {
+            println!("There is no running game with id {}.", id);
+            return;
+        }
+        message => panic!("Unexpected message: {:?}", message),
+    };
+    let mut game = Connect4::from_moves(&game_history).unwrap();
+    println!(
+        "You are watching game {}. Red plays with {} and yellow with {}.\n\n{}\n",
+        id,
+        theme.symbol(Player::Red),
+        theme.symbol(Player::Yellow),
+        board::render(&game, game_history.last().copied(), appearance)
+    );
+    loop {
+        match Message::receive_from(&mut server) {
+            ValidAction(action) => {
+                let player = game.to_play;
+                game.play(action as usize);
+                game_history.push(action);
+                println!(
+                    "\n{}\n\nThe {} player placed a token in column {}.\n",
+                    board::render(&game, Some(action), appearance),
+                    player,
+                    action as usize + appearance.first_column
+                );
+            }
+            ChatFrom(player, text) => println!("{}: {}", player, text),
+            Notice(text) => println!("Notice from the server: {}", text),
+            GameOver(winner, termination, _) => {
+                game_over(winner, termination);
+                break;
+            }
+            message => panic!("Unexpected message: {:?}", message),
+        }
+    }
+    if let Some(filename) = replay_file {
+        game.save(filename, game_history);
+    }
+}
+
+// Display the result of the game
+fn game_over(winner: Option<Player>, termination: Termination) {
+    match (winner, termination) {
+        (_, Termination::Adjourned) => println!("The game was stopped by the server."),
+        (None, Termination::Agreement) => println!("The players agreed to a draw."),
+        (None, _) => println!("The game ended in a draw."),
+        (Some(winner), Termination::Normal) => println!("The {} player won the game.", winner),
+        (Some(winner), Termination::TimeForfeit) => println!(
+            "The {} player ran out of time, the {} player won the game.",
+            winner.other(),
+            winner
+        ),
+        (Some(winner), Termination::Abandoned) => println!(
+            "The {} player left the game, the {} player won the game.",
+            winner.other(),
+            winner
+        ),
+        (Some(winner), Termination::Resignation) => println!(
+            "The {} player resigned, the {} player won the game.",
+            winner.other(),
+            winner
+        ),
+        (Some(_), Termination::Agreement) => unreachable!(),
+    }
+}
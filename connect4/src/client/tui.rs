@@ -0,0 +1,539 @@
+use super::{
+    board::{Appearance, Theme},
+    interface::Interface,
+};
+use crate::{
+    clock::{self, TimeControl},
+    communication::MAX_CHAT_LENGTH,
+    game_logic::{Cell, Connect4, Player, BOARD_HEIGHT, BOARD_WIDTH},
+};
+use ratatui::{
+    crossterm::event::{Event as TerminalEvent, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    DefaultTerminal, Frame,
+};
+use std::{
+    process,
+    time::{Duration, Instant},
+};
+
+// Number of messages kept in the message panel
+const LOG_LENGTH: usize = 200;
+
+// Keys given in the footer, after the numbers of the columns
+const HELP: &str = "play   c chat   : command   ? hint   e eval   t threats";
+
+// State of the game as shown on the screen
+struct Screen {
+    theme: Theme,
+    first_column: usize,
+    color: Player,
+    names: (String, String),
+    time_control: TimeControl,
+    game: Connect4,
+    moves: Vec<u8>,
+    column: usize,                 // column selected with the arrow keys
+    playing: bool,                 // whether a game is running
+    to_play: bool,                 // whether the server is waiting for a move of the player
+    clocks: Option<[Duration; 2]>, // remaining time of red and yellow when `since` was taken
+    running: Option<Player>,       // player whose clock is running
+    since: Instant,
+    log: Vec<Line<'static>>, // messages of the server, chat and results
+    chat: Option<String>,    // chat message or command being typed
+    command: bool,           // whether a command is typed rather than a chat message
+}
+
+// Full-screen interface drawn in the alternate screen of the terminal, which is restored when it
+// is dropped
+pub struct Tui {
+    terminal: DefaultTerminal,
+    screen: Screen,
+}
+
+// Guests and the AI of the client have no name
+fn display_name(name: &str) -> &str {
+    if name.is_empty() {
+        "Opponent"
+    } else {
+        name
+    }
+}
+
+fn index(player: Player) -> usize {
+    player.select(0, 1).0
+}
+
+impl Tui {
+    // Switch the terminal to the full-screen interface. The terminal is also restored on panics.
+    pub fn new(appearance: Appearance) -> Self {
+        let mut tui = Self {
+            terminal: ratatui::init(),
+            screen: Screen {
+                theme: appearance.theme,
+                first_column: appearance.first_column,
+                color: Player::Red,
+                names: (String::new(), String::new()),
+                time_control: TimeControl::Unlimited,
+                game: Connect4::new(),
+                moves: Vec::new(),
+                column: BOARD_WIDTH / 2,
+                playing: false,
+                to_play: false,
+                clocks: None,
+                running: None,
+                since: Instant::now(),
+                log: Vec::new(),
+                chat: None,
+                command: false,
+            },
+        };
+        tui.draw();
+        tui
+    }
+
+    fn draw(&mut self) {
+        let _ = self.terminal.draw(|frame| self.screen.render(frame));
+    }
+
+    fn log(&mut self, line: Line<'static>) {
+        let log = &mut self.screen.log;
+        log.push(line);
+        if log.len() > LOG_LENGTH {
+            log.remove(0);
+        }
+        self.draw();
+    }
+}
+
+impl Interface for Tui {
+    fn start(&mut self, color: Player, time_control: TimeControl) {
+        let screen = &mut self.screen;
+        screen.color = color;
+        screen.names = (String::new(), String::new());
+        screen.time_control = time_control;
+        screen.game = Connect4::new();
+        screen.moves.clear();
+        screen.column = BOARD_WIDTH / 2;
+        screen.playing = true;
+        screen.to_play = false;
+        screen.clocks = match time_control {
+            TimeControl::Unlimited => None,
+            TimeControl::Increment(base, _) => Some([base; 2]),
+            TimeControl::PerMove(time) => Some([time; 2]),
+        };
+        screen.running = Some(Player::Red);
+        screen.since = Instant::now();
+        let text = format!("New game: you play {} with {}.", color, time_control);
+        self.log(Line::from(text).bold());
+    }
+
+    fn players(&mut self, names: (String, String)) {
+        self.screen.names = names;
+        self.draw();
+    }
+
+    fn moved(&mut self, game: &Connect4, action: u8) {
+        let screen = &mut self.screen;
+        let player = game.to_play.other();
+        // The clocks are counted down locally between the requests for a move of the server
+        if let (Some(clocks), Some(running)) = (&mut screen.clocks, screen.running) {
+            let clock = &mut clocks[index(running)];
+            *clock = clock.saturating_sub(screen.since.elapsed());
+            match screen.time_control {
+                TimeControl::Increment(_, increment) => *clock += increment,
+                TimeControl::PerMove(time) => *clock = time,
+                TimeControl::Unlimited => (),
+            }
+        }
+        screen.running = Some(game.to_play).filter(|_| !game.over());
+        screen.since = Instant::now();
+        screen.game = game.clone();
+        screen.moves.push(action);
+        if player == screen.color {
+            screen.to_play = false;
+        }
+        self.draw();
+    }
+
+    fn resumed(&mut self, game: &Connect4, history: &[u8]) {
+        let screen = &mut self.screen;
+        screen.game = game.clone();
+        screen.moves = history.to_vec();
+        screen.to_play = false;
+        screen.running = None;
+        self.log(Line::from("The game has been resumed."));
+    }
+
+    fn ask_move(&mut self, clocks: Option<(Duration, Duration)>) {
+        let screen = &mut self.screen;
+        if let Some((remaining, other_remaining)) = clocks {
+            let (red, yellow) = screen.color.select(remaining, other_remaining);
+            screen.clocks = Some([red, yellow]);
+        }
+        screen.running = Some(screen.color);
+        screen.since = Instant::now();
+        screen.to_play = true;
+        self.draw();
+    }
+
+    fn invalid_move(&mut self) {
+        self.screen.to_play = true;
+        self.log(Line::from("This column is full, choose another one.").red());
+    }
+
+    fn chat(&mut self, text: &str) {
+        let screen = &self.screen;
+        let name = screen.opponent_name();
+        let line = Line::from(vec![
+            Span::styled(format!("{}: ", name), screen.style(screen.color.other())),
+            Span::raw(text.to_owned()),
+        ]);
+        self.log(line);
+    }
+
+    fn game_over(&mut self, result: &str) {
+        let screen = &mut self.screen;
+        screen.playing = false;
+        screen.to_play = false;
+        screen.running = None;
+        self.log(Line::from(result.to_owned()).bold());
+    }
+
+    fn status(&mut self, text: &str) {
+        for line in text.trim().lines() {
+            self.log(Line::from(line.to_owned()));
+        }
+    }
+
+    fn handle(&mut self, event: TerminalEvent) -> Option<String> {
+        let key = match event {
+            TerminalEvent::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => {
+                self.draw();
+                return None;
+            }
+        };
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            // The terminal is in raw mode, so Ctrl-C does not interrupt the client by itself
+            ratatui::restore();
+            process::exit(130);
+        }
+        let screen = &mut self.screen;
+        let line = if let Some(chat) = &mut screen.chat {
+            match key.code {
+                KeyCode::Enter => {
+                    let text = screen.chat.take().unwrap();
+                    let text = text.trim().to_owned();
+                    if text.is_empty() {
+                        None
+                    } else if screen.command {
+                        Some(text)
+                    } else {
+                        let you = Span::styled("You: ", screen.style(screen.color));
+                        self.log(Line::from(vec![you, Span::raw(text.clone())]));
+                        Some(format!("chat {}", text))
+                    }
+                }
+                KeyCode::Esc => {
+                    screen.chat = None;
+                    None
+                }
+                KeyCode::Backspace => {
+                    chat.pop();
+                    None
+                }
+                KeyCode::Char(character)
+                    if chat.len() + character.len_utf8() <= MAX_CHAT_LENGTH =>
+                {
+                    chat.push(character);
+                    None
+                }
+                _ => None,
+            }
+        } else {
+            match key.code {
+                KeyCode::Left | KeyCode::Char('h') => {
+                    screen.column = screen.column.saturating_sub(1);
+                    None
+                }
+                KeyCode::Right | KeyCode::Char('l') => {
+                    screen.column = (screen.column + 1).min(BOARD_WIDTH - 1);
+                    None
+                }
+                KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Down if screen.playing => {
+                    Some((screen.column + screen.first_column).to_string())
+                }
+                KeyCode::Char(digit)
+                    if screen.playing
+                        && digit.to_digit(10).is_some_and(|number| {
+                            (screen.first_column..screen.first_column + BOARD_WIDTH)
+                                .contains(&(number as usize))
+                        }) =>
+                {
+                    screen.column = digit.to_digit(10).unwrap() as usize - screen.first_column;
+                    Some(digit.to_string())
+                }
+                KeyCode::Char(key @ ('c' | '/' | ':')) => {
+                    screen.chat = Some(String::new());
+                    screen.command = key == ':';
+                    None
+                }
+                // Help of the AI, which the client may refuse
+                KeyCode::Char('?') => Some(String::from("hint")),
+                KeyCode::Char('e') => Some(String::from("eval")),
+                KeyCode::Char('t') => Some(String::from("threats")),
+                // Answers to the offer of a rematch
+                KeyCode::Char(answer @ ('y' | 'n')) if !screen.playing => Some(answer.to_string()),
+                _ => None,
+            }
+        };
+        self.draw();
+        line
+    }
+
+    fn tick(&mut self) {
+        if self.screen.running.is_some() {
+            self.draw();
+        }
+    }
+}
+
+impl Drop for Tui {
+    // Leave the final board and the last messages on the normal screen
+    fn drop(&mut self) {
+        ratatui::restore();
+        println!("{}\n", self.screen.game);
+        let log = &self.screen.log;
+        for line in &log[log.len().saturating_sub(5)..] {
+            println!("{}", line);
+        }
+    }
+}
+
+impl Screen {
+    fn render(&self, frame: &mut Frame) {
+        let [title, main, messages, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(BOARD_HEIGHT as u16 + 7),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [board, side] = Layout::horizontal([
+            Constraint::Length(4 * BOARD_WIDTH as u16 + 3),
+            Constraint::Min(20),
+        ])
+        .areas(main);
+        let [players, moves] =
+            Layout::vertical([Constraint::Length(4), Constraint::Min(3)]).areas(side);
+        frame.render_widget(Paragraph::new(self.title()), title);
+        frame.render_widget(
+            Paragraph::new(self.board()).block(Block::bordered().title(" Board ")),
+            board,
+        );
+        frame.render_widget(
+            Paragraph::new(self.players()).block(Block::bordered().title(" Players ")),
+            players,
+        );
+        let visible = moves.height.saturating_sub(2) as usize;
+        frame.render_widget(
+            Paragraph::new(self.moves(visible)).block(Block::bordered().title(" Moves ")),
+            moves,
+        );
+        self.render_messages(frame, messages);
+        let footer_line = match &self.chat {
+            Some(chat) => Line::from(vec![
+                Span::raw(if self.command {
+                    "Command (Enter run, Esc cancel, help for the list): "
+                } else {
+                    "Chat (Enter send, Esc cancel): "
+                })
+                .bold(),
+                Span::raw(chat.clone()),
+                Span::raw("_").add_modifier(Modifier::SLOW_BLINK),
+            ]),
+            None => Line::from(format!(
+                "←/→ select   Enter drop   {}-{} {}",
+                self.first_column,
+                self.first_column + BOARD_WIDTH - 1,
+                HELP
+            ))
+            .dim(),
+        };
+        frame.render_widget(Paragraph::new(footer_line), footer);
+    }
+
+    fn render_messages(&self, frame: &mut Frame, area: Rect) {
+        let visible = area.height.saturating_sub(2) as usize;
+        let log = &self.log[self.log.len().saturating_sub(visible)..];
+        frame.render_widget(
+            Paragraph::new(log.to_vec()).block(Block::bordered().title(" Messages ")),
+            area,
+        );
+    }
+
+    // Style of the tokens of the player, bold if the theme has no colors
+    fn style(&self, player: Player) -> Style {
+        match self.theme.color(player) {
+            Some(color) => Style::new().fg(Color::Indexed(color)),
+            None => Style::new().add_modifier(Modifier::BOLD),
+        }
+    }
+
+    // Number of a column as shown to the player
+    fn column_name(&self, action: u8) -> String {
+        (action as usize + self.first_column).to_string()
+    }
+
+    fn opponent_name(&self) -> &str {
+        let opponent = self.color.other();
+        display_name(opponent.select(&self.names.0, &self.names.1).0)
+    }
+
+    fn title(&self) -> Line<'static> {
+        let text = if !self.playing {
+            String::from("Connect 4")
+        } else if self.to_play {
+            String::from("Connect 4 - your turn")
+        } else {
+            format!("Connect 4 - {} to play", self.opponent_name())
+        };
+        Line::from(text).bold()
+    }
+
+    // Board with the token of the player above the selected column, and the cell where it would
+    // land, when it is their turn
+    fn board(&self) -> Vec<Line<'static>> {
+        let mine = self.style(self.color);
+        let token = format!(" {} ", self.theme.symbol(self.color));
+        let last_move = self.moves.last().map(|&column| {
+            let column = column as usize;
+            let row = (0..BOARD_HEIGHT)
+                .rev()
+                .find(|&row| self.game[(row, column)] != Cell::Empty);
+            (row.unwrap_or(0), column)
+        });
+        let winning = self.game.winning_lines().concat();
+        let landing = (0..BOARD_HEIGHT).find(|&row| self.game[(row, self.column)] == Cell::Empty);
+        let mut lines = Vec::new();
+        let mut preview = vec![Span::raw(" ")];
+        for column in 0..BOARD_WIDTH {
+            let token = match (column == self.column, self.to_play) {
+                (true, true) => Span::styled(token.clone(), mine),
+                (true, false) => Span::raw(" ▼ ").dim(),
+                _ => Span::raw("   "),
+            };
+            preview.extend([token, Span::raw(" ")]);
+        }
+        lines.push(Line::from(preview));
+        for row in (0..BOARD_HEIGHT).rev() {
+            let mut spans = vec![Span::raw(" │")];
+            for column in 0..BOARD_WIDTH {
+                let player = match self.game[(row, column)] {
+                    Cell::Red => Player::Red,
+                    Cell::Yellow => Player::Yellow,
+                    Cell::Empty
+                        if self.to_play && column == self.column && landing == Some(row) =>
+                    {
+                        spans.extend([Span::styled(token.clone(), mine).dim(), Span::raw("│")]);
+                        continue;
+                    }
+                    Cell::Empty => {
+                        spans.extend([Span::raw("   "), Span::raw("│")]);
+                        continue;
+                    }
+                };
+                let symbol = self.theme.symbol(player);
+                let text = if last_move == Some((row, column)) {
+                    format!("[{}]", symbol)
+                } else {
+                    format!(" {} ", symbol)
+                };
+                let mut cell = Span::styled(text, self.style(player));
+                if winning.contains(&(row, column)) {
+                    cell = cell.reversed();
+                }
+                spans.extend([cell, Span::raw("│")]);
+            }
+            lines.push(Line::from(spans));
+        }
+        lines.push(Line::from(format!(
+            " └{}───┘",
+            "───┴".repeat(BOARD_WIDTH - 1)
+        )));
+        let mut numbers = vec![Span::raw(" ")];
+        for column in 0..BOARD_WIDTH {
+            let number = Span::raw(format!(" {} ", column + self.first_column));
+            let number = if column == self.column {
+                number.reversed()
+            } else {
+                number
+            };
+            numbers.extend([number, Span::raw(" ")]);
+        }
+        lines.push(Line::from(numbers));
+        lines
+    }
+
+    // Names and clocks of both players, the player to move is marked
+    fn players(&self) -> Vec<Line<'static>> {
+        [Player::Red, Player::Yellow]
+            .into_iter()
+            .map(|player| {
+                let name = if player == self.color {
+                    String::from("You")
+                } else {
+                    self.opponent_name().to_owned()
+                };
+                let marker = if self.running == Some(player) {
+                    "▶ "
+                } else {
+                    "  "
+                };
+                let mut spans = vec![
+                    Span::raw(marker),
+                    Span::styled(
+                        format!("{} ", self.theme.symbol(player)),
+                        self.style(player),
+                    ),
+                    Span::raw(format!("{:<12}", name)),
+                ];
+                if let Some(clocks) = self.clocks {
+                    let mut remaining = clocks[index(player)];
+                    if self.running == Some(player) {
+                        remaining = remaining.saturating_sub(self.since.elapsed());
+                    }
+                    spans.push(Span::raw(clock::format_duration(remaining)).bold());
+                }
+                Line::from(spans)
+            })
+            .collect()
+    }
+
+    // Last moves, one line per pair of moves
+    fn moves(&self, visible: usize) -> Vec<Line<'static>> {
+        let lines: Vec<Line> = self
+            .moves
+            .chunks(2)
+            .enumerate()
+            .map(|(number, pair)| {
+                let mut spans = vec![
+                    Span::raw(format!("{:>3}. ", number + 1)).dim(),
+                    Span::styled(self.column_name(pair[0]), self.style(Player::Red)),
+                ];
+                if let Some(action) = pair.get(1) {
+                    spans.push(Span::raw("  "));
+                    spans.push(Span::styled(
+                        self.column_name(*action),
+                        self.style(Player::Yellow),
+                    ));
+                }
+                Line::from(spans)
+            })
+            .collect();
+        lines[lines.len().saturating_sub(visible)..].to_vec()
+    }
+}
@@ -0,0 +1,117 @@
+use crate::game_logic::Player;
+use std::{fmt, time::Duration};
+
+// Time control of a game, chosen when starting the server
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TimeControl {
+    Unlimited,
+    Increment(Duration, Duration), // base time, then time added after each move
+    PerMove(Duration),             // fixed time for each move, unused time is lost
+}
+
+impl TimeControl {
+    // Time available to each player at the start of the game
+    fn initial_time(self) -> Duration {
+        use TimeControl::*;
+        match self {
+            Unlimited => Duration::MAX,
+            Increment(base, _) | PerMove(base) => base,
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TimeControl::*;
+        match self {
+            Unlimited => write!(f, "unlimited time"),
+            Increment(base, increment) if increment.is_zero() => {
+                write!(f, "{} per player", format_duration(*base))
+            }
+            Increment(base, increment) => write!(
+                f,
+                "{} per player + {} per move",
+                format_duration(*base),
+                format_duration(*increment)
+            ),
+            PerMove(time) => write!(f, "{} per move", format_duration(*time)),
+        }
+    }
+}
+
+// Format a duration as minutes, seconds and tenths of seconds
+pub fn format_duration(duration: Duration) -> String {
+    let tenths = duration.as_millis() / 100;
+    let (minutes, seconds, tenths) = (tenths / 600, tenths / 10 % 60, tenths % 10);
+    if minutes > 0 {
+        format!("{}:{:02}.{}", minutes, seconds, tenths)
+    } else {
+        format!("{}.{}s", seconds, tenths)
+    }
+}
+
+// Clocks of both players, maintained by the server
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Clock {
+    time_control: TimeControl,
+    remaining: [Duration; 2], // remaining time for red and yellow
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Self {
+        let initial_time = time_control.initial_time();
+        Self {
+            time_control,
+            remaining: [initial_time; 2],
+        }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.time_control
+    }
+
+    // Remaining time of a player, None if the time is unlimited
+    pub fn remaining(&self, player: Player) -> Option<Duration> {
+        match self.time_control {
+            TimeControl::Unlimited => None,
+            _ => Some(self.remaining[Self::index(player)]),
+        }
+    }
+
+    // Remaining times of a player and of the other player, as sent with Play
+    pub fn remaining_pair(&self, player: Player) -> Option<(Duration, Duration)> {
+        Some((self.remaining(player)?, self.remaining(player.other())?))
+    }
+
+    // Remove the time spent thinking from the clock, returns false if the player ran out of time
+    pub fn spend(&mut self, player: Player, elapsed: Duration) -> bool {
+        if let TimeControl::Unlimited = self.time_control {
+            return true;
+        }
+        let remaining = &mut self.remaining[Self::index(player)];
+        match remaining.checked_sub(elapsed) {
+            Some(left) if !left.is_zero() => {
+                *remaining = left;
+                true
+            }
+            _ => {
+                *remaining = Duration::ZERO;
+                false
+            }
+        }
+    }
+
+    // Update the clock of a player once their move has been accepted
+    pub fn complete_move(&mut self, player: Player) {
+        let remaining = &mut self.remaining[Self::index(player)];
+        match self.time_control {
+            TimeControl::Unlimited => (),
+            TimeControl::Increment(_, increment) => *remaining += increment,
+            TimeControl::PerMove(time) => *remaining = time,
+        }
+    }
+
+    fn index(player: Player) -> usize {
+        player.select(0, 1).0
+    }
+}
@@ -0,0 +1,670 @@
+use crate::{
+    archive::{GameRecord, Outcome, Query},
+    clock::TimeControl,
+    game_logic::{
+        Connect4, Line,
+        Player::{self, *},
+        BOARD_HEIGHT, BOARD_WIDTH,
+    },
+};
+use std::{
+    convert::TryInto,
+    io::{self, ErrorKind, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub mod transport;
+
+const MAX_MESSAGE_SIZE: usize = 1_024;
+
+// Maximum size of a chat message and of the name of a room in bytes
+pub const MAX_CHAT_LENGTH: usize = 280;
+pub const MAX_ROOM_NAME_LENGTH: usize = 40;
+pub const MAX_ROOM_CODE_LENGTH: usize = 16;
+
+// Maximum size of the name and of the password of an account in bytes
+pub const MAX_ACCOUNT_NAME_LENGTH: usize = 20;
+pub const MAX_PASSWORD_LENGTH: usize = 64;
+
+// Message enumeration to communicate between the client and the server
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Message {
+    // First message of a client, to look for a new game, to come back to a game with a resume
+    // token or to watch the game with the given id
+    Join,
+    Resume(u64),
+    Spectate(u32),
+    // Other first messages, to create a room with a name (private rooms are not listed), to join
+    // a room with its invite code or to list the public rooms
+    CreateRoom(String, bool),
+    JoinRoom(String),
+    ListRooms,
+    // Answers to CreateRoom and ListRooms: invite code of the room, codes and names of the rooms
+    RoomCode(String),
+    Rooms(Vec<(String, String)>),
+    // Optional first message, followed by one of the above: log in or register (true) with a name
+    // and a password
+    Login(String, String, bool),
+    // Rating of a player, sent when they log in and after each of their rated games
+    Rating(u16),
+    LoginFailed,
+    // Other first message, to search the archive of the server. The games found are sent one by
+    // one, followed by EndOfSearch.
+    Search(Query),
+    ArchivedGame(GameRecord),
+    EndOfSearch,
+    // Other first message, to play against a bot of the server of the given level
+    PlayBot(u8),
+    // Other first message, after logging in, to take part in the tournament of the server. The
+    // games of the tournament then follow one another on the connection, until the final rank of
+    // the player among the participants is sent.
+    JoinTournament,
+    Registered,
+    TournamentOver(u16, u16),
+    // Score of a series of games in half points, for the player and their opponent, sent after
+    // each result. Once the series is over, the players are offered a rematch with the colors
+    // swapped, which they accept or decline. It starts with Hello if both accept.
+    Score(u8, u8),
+    OfferRematch,
+    Rematch(bool),
+    NoRematch,
+    // Start of a game: color and time control, then resume token and id of the game, names of the
+    // red and yellow players (accounts or levels of bots, empty for guests) and whether the server
+    // lets the players ask their client for the help of the AI
+    Hello(Player, TimeControl),
+    Token(u64),
+    GameId(u32),
+    Players(String, String),
+    Hints(bool),
+    // Moves already played, sent when resuming or watching a game
+    History(Vec<u8>),
+    // No running game matches the request
+    NotFound,
+    // Request for a move, with the remaining time of the player and of the opponent
+    Play(Option<(Duration, Duration)>),
+    Action(u8),
+    ValidAction(u8),
+    InvalidAction,
+    // A player gives up the game, or offers a draw, which is relayed to their opponent. The game
+    // ends in a draw if the opponent offers one too before playing their next move.
+    Resign,
+    OfferDraw,
+    // Results, with the lines completed by the winning move if the game ended normally
+    Lose(Termination, Vec<Line>),
+    Draw,
+    Win(Termination, Vec<Line>),
+    // Winner of the game, sent to spectators
+    GameOver(Option<Player>, Termination, Vec<Line>),
+    // Chat message of a player, relayed by the server with the player who wrote it
+    Chat(String),
+    ChatFrom(Player, String),
+    // Notice of the administrator of the server, sent to the players and the spectators
+    Notice(String),
+}
+
+// Reason why a game ended
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Termination {
+    Normal,      // four in a row or full board
+    TimeForfeit, // a player ran out of time
+    Abandoned,   // a player disconnected and did not come back in time
+    Adjourned,   // the server stopped the game, which has no result
+    Resignation, // a player gave up
+    Agreement,   // both players agreed to a draw
+}
+
+use Message::*;
+
+impl Message {
+    pub fn receive_from(player: &mut TcpStream) -> Self {
+        Message::read_from(player).unwrap()
+    }
+
+    pub fn send_to(self, player: &mut TcpStream) {
+        self.write_to(player).unwrap();
+    }
+
+    // Same as send_to, but lets the caller handle a lost connection or an invalid message
+    pub fn write_to(&self, player: &mut TcpStream) -> io::Result<()> {
+        player.write_all(&self.to_frame()?)
+    }
+
+    // Messages are prefixed by their length so that consecutive messages are not merged. An
+    // invalid message is consumed entirely and reported with the InvalidData error kind, so that
+    // the next messages can still be read.
+    pub fn read_from(player: &mut TcpStream) -> io::Result<Self> {
+        let mut length = [0; 2];
+        player.read_exact(&mut length)?;
+        let mut buffer = vec![0; u16::from_be_bytes(length) as usize];
+        player.read_exact(&mut buffer)?;
+        Message::from_bytes(&buffer)
+    }
+
+    // Non-blocking version of write_to, used by the server
+    pub async fn write_async<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_frame()?).await
+    }
+
+    // Non-blocking version of read_from, used by the server
+    pub async fn read_async<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Self> {
+        let length = reader.read_u16().await?;
+        let mut buffer = vec![0; length as usize];
+        reader.read_exact(&mut buffer).await?;
+        Message::from_bytes(&buffer)
+    }
+
+    // Bytes of the message prefixed by their length
+    fn to_frame(&self) -> io::Result<Vec<u8>> {
+        let bytes = self.to_bytes();
+        // Refuse to send a message that the other side would refuse to read
+        Message::from_bytes(&bytes)
+            .map_err(|error| io::Error::new(ErrorKind::InvalidInput, error.to_string()))?;
+        let mut frame = (bytes.len() as u16).to_be_bytes().to_vec();
+        frame.extend(bytes);
+        Ok(frame)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(invalid_data(format!(
+                "message too long: {} bytes",
+                bytes.len()
+            )));
+        }
+        Ok(match *bytes {
+            [0, 0, ref time_control @ ..] => Hello(Red, decode_time_control(time_control)?),
+            [0, 1, ref time_control @ ..] => Hello(Yellow, decode_time_control(time_control)?),
+            [0, 2] => Join,
+            [0, 3, ref token @ ..] if token.len() == 8 => Resume(decode_token(token)),
+            [0, 4, ref token @ ..] if token.len() == 8 => Token(decode_token(token)),
+            [0, 5, ref history @ ..] => History(check_history(history)?),
+            [0, 6] => NotFound,
+            [0, 7, ref id @ ..] if id.len() == 4 => Spectate(decode_id(id)),
+            [0, 8, ref id @ ..] if id.len() == 4 => GameId(decode_id(id)),
+            [0, 9, private @ (0 | 1), ref name @ ..] => {
+                CreateRoom(check_room_name(name)?, private == 1)
+            }
+            [0, 10, ref code @ ..] => JoinRoom(check_code(code)?),
+            [0, 11] => ListRooms,
+            [0, 12, ref code @ ..] => RoomCode(check_code(code)?),
+            [0, 13, ref rooms @ ..] => Rooms(decode_rooms(rooms)?),
+            [0, 14, register @ (0 | 1), ref rest @ ..] => {
+                let mut rest = rest;
+                let name = check_account_name(next_text(&mut rest)?)?;
+                let password = check_text(rest, 1, MAX_PASSWORD_LENGTH, "passwords")?;
+                Login(name, password, register == 1)
+            }
+            [0, 15, high, low] => Rating(u16::from_be_bytes([high, low])),
+            [0, 16] => LoginFailed,
+            [0, 17, ref query @ ..] => Search(decode_query(query)?),
+            [0, 18, ref game @ ..] => ArchivedGame(decode_game(game)?),
+            [0, 19] => EndOfSearch,
+            [0, 20, level] => PlayBot(level),
+            [0, 21] => JoinTournament,
+            [0, 22] => Registered,
+            [0, 23, rank_high, rank_low, count_high, count_low] => TournamentOver(
+                u16::from_be_bytes([rank_high, rank_low]),
+                u16::from_be_bytes([count_high, count_low]),
+            ),
+            [0, 24, score, opponent_score] => Score(score, opponent_score),
+            [0, 25] => OfferRematch,
+            [0, 26, accept @ (0 | 1)] => Rematch(accept == 1),
+            [0, 27] => NoRematch,
+            [0, 28, ref names @ ..] => {
+                let mut rest = names;
+                let red = check_optional_account(next_text(&mut rest)?)?;
+                let yellow = check_optional_account(next_text(&mut rest)?)?;
+                Players(red, yellow)
+            }
+            [0, 29, allowed @ (0 | 1)] => Hints(allowed == 1),
+            [0, 30] => Resign,
+            [0, 31] => OfferDraw,
+            [1, 0] => Play(None),
+            [1, 0, ref times @ ..] if times.len() == 8 => Play(Some((
+                decode_duration(&times[..4]),
+                decode_duration(&times[4..]),
+            ))),
+            [1, 1, action, ..] => Action(action),
+            [1, 2, action, ..] => ValidAction(action),
+            [1, 3, ..] => InvalidAction,
+            [2, 0, termination, ref lines @ ..] => {
+                Lose(decode_termination(termination)?, decode_lines(lines)?)
+            }
+            [2, 1, ..] => Draw,
+            [2, 2, termination, ref lines @ ..] => {
+                Win(decode_termination(termination)?, decode_lines(lines)?)
+            }
+            [2, 3, winner @ (0..=2), termination, ref lines @ ..] => GameOver(
+                [Some(Red), Some(Yellow), None][winner as usize],
+                decode_termination(termination)?,
+                decode_lines(lines)?,
+            ),
+            [3, 0, ref text @ ..] => Chat(check_chat(text)?),
+            [3, 1, 0, ref text @ ..] => ChatFrom(Red, check_chat(text)?),
+            [3, 1, 1, ref text @ ..] => ChatFrom(Yellow, check_chat(text)?),
+            [3, 2, ref text @ ..] => Notice(check_chat(text)?),
+            _ => {
+                return Err(invalid_data(format!(
+                    "bytes cannot be converted to a message: {:?}",
+                    bytes
+                )))
+            }
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match *self {
+            Join => vec![0, 2],
+            Resume(token) => [&[0, 3], &token.to_be_bytes()[..]].concat(),
+            Token(token) => [&[0, 4], &token.to_be_bytes()[..]].concat(),
+            History(ref history) => [&[0, 5], &history[..]].concat(),
+            NotFound => vec![0, 6],
+            Spectate(id) => [&[0, 7], &id.to_be_bytes()[..]].concat(),
+            GameId(id) => [&[0, 8], &id.to_be_bytes()[..]].concat(),
+            CreateRoom(ref name, private) => [&[0, 9, private as u8], name.as_bytes()].concat(),
+            JoinRoom(ref code) => [&[0, 10], code.as_bytes()].concat(),
+            ListRooms => vec![0, 11],
+            RoomCode(ref code) => [&[0, 12], code.as_bytes()].concat(),
+            Rooms(ref rooms) => {
+                let mut bytes = vec![0, 13];
+                for (code, name) in rooms {
+                    bytes.push(code.len() as u8);
+                    bytes.extend(code.as_bytes());
+                    bytes.push(name.len() as u8);
+                    bytes.extend(name.as_bytes());
+                }
+                bytes
+            }
+            Login(ref name, ref password, register) => {
+                let mut bytes = vec![0, 14, register as u8, name.len() as u8];
+                bytes.extend(name.as_bytes());
+                bytes.extend(password.as_bytes());
+                bytes
+            }
+            Rating(rating) => [&[0, 15], &rating.to_be_bytes()[..]].concat(),
+            LoginFailed => vec![0, 16],
+            Search(ref query) => {
+                let outcome = match query.outcome {
+                    None => 0,
+                    Some(Outcome::RedWins) => 1,
+                    Some(Outcome::YellowWins) => 2,
+                    Some(Outcome::Draw) => 3,
+                };
+                let player = query.player.as_deref().unwrap_or("");
+                let mut bytes = vec![0, 17, outcome];
+                bytes.extend(query.from.unwrap_or(0).to_be_bytes());
+                bytes.extend(query.to.unwrap_or(u64::MAX).to_be_bytes());
+                bytes.push(player.len() as u8);
+                bytes.extend(player.as_bytes());
+                bytes.extend(&query.opening);
+                bytes
+            }
+            ArchivedGame(ref game) => {
+                let winner = game.winner.map_or(2, |winner| winner.select(0, 1).0);
+                let mut bytes = vec![0, 18];
+                bytes.extend(game.number.to_be_bytes());
+                bytes.extend(game.start.to_be_bytes());
+                bytes.extend(game.end.to_be_bytes());
+                bytes.extend([winner, encode_termination(game.termination)]);
+                for player in [&game.players.0, &game.players.1] {
+                    bytes.push(player.len() as u8);
+                    bytes.extend(player.as_bytes());
+                }
+                bytes.extend(&game.moves);
+                bytes
+            }
+            EndOfSearch => vec![0, 19],
+            PlayBot(level) => vec![0, 20, level],
+            JoinTournament => vec![0, 21],
+            Registered => vec![0, 22],
+            TournamentOver(rank, count) => {
+                [&[0, 23], &rank.to_be_bytes()[..], &count.to_be_bytes()[..]].concat()
+            }
+            Score(score, opponent_score) => vec![0, 24, score, opponent_score],
+            OfferRematch => vec![0, 25],
+            Rematch(accept) => vec![0, 26, accept as u8],
+            NoRematch => vec![0, 27],
+            Players(ref red, ref yellow) => {
+                let mut bytes = vec![0, 28];
+                for player in [red, yellow] {
+                    bytes.push(player.len() as u8);
+                    bytes.extend(player.as_bytes());
+                }
+                bytes
+            }
+            Hints(allowed) => vec![0, 29, allowed as u8],
+            Resign => vec![0, 30],
+            OfferDraw => vec![0, 31],
+            Hello(player, time_control) => {
+                let mut bytes = vec![0, player.select(0, 1).0];
+                bytes.extend(encode_time_control(time_control));
+                bytes
+            }
+            Play(None) => vec![1, 0],
+            Play(Some((remaining, other_remaining))) => {
+                let mut bytes = vec![1, 0];
+                bytes.extend(encode_duration(remaining));
+                bytes.extend(encode_duration(other_remaining));
+                bytes
+            }
+            Action(action) => vec![1, 1, action],
+            ValidAction(action) => vec![1, 2, action],
+            InvalidAction => vec![1, 3],
+            Lose(termination, ref lines) => [
+                &[2, 0, encode_termination(termination)],
+                &encode_lines(lines)[..],
+            ]
+            .concat(),
+            Draw => vec![2, 1],
+            Win(termination, ref lines) => [
+                &[2, 2, encode_termination(termination)],
+                &encode_lines(lines)[..],
+            ]
+            .concat(),
+            GameOver(winner, termination, ref lines) => {
+                let winner = winner.map_or(2, |winner| winner.select(0, 1).0);
+                let bytes = [2, 3, winner, encode_termination(termination)];
+                [&bytes[..], &encode_lines(lines)[..]].concat()
+            }
+            Chat(ref text) => [&[3, 0], text.as_bytes()].concat(),
+            ChatFrom(player, ref text) => {
+                [&[3, 1, player.select(0, 1).0], text.as_bytes()].concat()
+            }
+            Notice(ref text) => [&[3, 2], text.as_bytes()].concat(),
+        }
+    }
+}
+
+fn invalid_data(error: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, error)
+}
+
+// Texts must be valid UTF-8 and their size in bytes must be within the given bounds
+fn check_text(
+    bytes: &[u8],
+    min_length: usize,
+    max_length: usize,
+    what: &str,
+) -> io::Result<String> {
+    if bytes.len() < min_length || bytes.len() > max_length {
+        return Err(invalid_data(format!(
+            "{} must contain between {} and {} bytes, not {}",
+            what,
+            min_length,
+            max_length,
+            bytes.len()
+        )));
+    }
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| invalid_data(format!("{} must be valid UTF-8", what)))
+}
+
+fn check_chat(bytes: &[u8]) -> io::Result<String> {
+    let text = check_text(bytes, 1, MAX_CHAT_LENGTH, "chat messages")?;
+    Ok(printable(text))
+}
+
+fn check_room_name(bytes: &[u8]) -> io::Result<String> {
+    let name = check_text(bytes, 0, MAX_ROOM_NAME_LENGTH, "room names")?;
+    Ok(printable(name))
+}
+
+// Control characters of the texts shown to the other players are replaced, so that they cannot
+// move the cursor or clear their terminal
+fn printable(text: String) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() {
+                char::REPLACEMENT_CHARACTER
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+// Invite codes only contain ASCII letters and digits
+fn check_code(bytes: &[u8]) -> io::Result<String> {
+    let code = check_text(bytes, 1, MAX_ROOM_CODE_LENGTH, "room codes")?;
+    if code.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+        Ok(code)
+    } else {
+        Err(invalid_data(format!("invalid room code: {:?}", code)))
+    }
+}
+
+// Moves of a game, which must be valid
+fn check_history(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    match Connect4::from_moves(bytes) {
+        Some(_) => Ok(bytes.to_vec()),
+        None => Err(invalid_data(format!("invalid history: {:?}", bytes))),
+    }
+}
+
+// Account names only contain ASCII letters, digits, '-' and '_'
+pub fn valid_account_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_ACCOUNT_NAME_LENGTH
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+fn check_account_name(bytes: &[u8]) -> io::Result<String> {
+    let name = check_text(bytes, 1, MAX_ACCOUNT_NAME_LENGTH, "account names")?;
+    if valid_account_name(&name) {
+        Ok(name)
+    } else {
+        Err(invalid_data(format!("invalid account name: {:?}", name)))
+    }
+}
+
+// Accounts may be empty in queries, archived games and names of players, which stands for any
+// player and guests
+fn check_optional_account(bytes: &[u8]) -> io::Result<String> {
+    if bytes.is_empty() {
+        Ok(String::new())
+    } else {
+        check_account_name(bytes)
+    }
+}
+
+// A query is sent as the outcome, the time range, the player and the opening. The bounds of the
+// time range are 0 and u64::MAX when they are missing, the player is empty.
+fn decode_query(bytes: &[u8]) -> io::Result<Query> {
+    let (outcome, bytes) = match bytes {
+        [outcome, rest @ ..] if rest.len() >= 16 => (*outcome, rest),
+        _ => return Err(invalid_data(String::from("truncated query"))),
+    };
+    let outcome = match outcome {
+        0 => None,
+        1 => Some(Outcome::RedWins),
+        2 => Some(Outcome::YellowWins),
+        3 => Some(Outcome::Draw),
+        _ => return Err(invalid_data(format!("invalid outcome: {}", outcome))),
+    };
+    let from = decode_time(&bytes[..8]);
+    let to = decode_time(&bytes[8..16]);
+    let mut rest = &bytes[16..];
+    let player = check_optional_account(next_text(&mut rest)?)?;
+    Ok(Query {
+        player: Some(player).filter(|player| !player.is_empty()),
+        from: Some(from).filter(|&from| from != 0),
+        to: Some(to).filter(|&to| to != u64::MAX),
+        outcome,
+        opening: check_moves(rest)?,
+    })
+}
+
+fn decode_game(bytes: &[u8]) -> io::Result<GameRecord> {
+    if bytes.len() < 22 {
+        return Err(invalid_data(String::from("truncated archived game")));
+    }
+    let (winner, termination, mut rest) = (bytes[20], bytes[21], &bytes[22..]);
+    let winner = match winner {
+        0 => Some(Red),
+        1 => Some(Yellow),
+        2 => None,
+        _ => return Err(invalid_data(format!("invalid winner: {}", winner))),
+    };
+    let red = check_optional_account(next_text(&mut rest)?)?;
+    let yellow = check_optional_account(next_text(&mut rest)?)?;
+    Ok(GameRecord {
+        number: decode_id(&bytes[..4]),
+        players: (red, yellow),
+        start: decode_time(&bytes[4..12]),
+        end: decode_time(&bytes[12..20]),
+        winner,
+        termination: decode_termination(termination)?,
+        moves: check_moves(rest)?,
+    })
+}
+
+// Moves are columns of the board
+fn check_moves(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    if bytes.iter().all(|&action| (action as usize) < BOARD_WIDTH) {
+        Ok(bytes.to_vec())
+    } else {
+        Err(invalid_data(format!("invalid moves: {:?}", bytes)))
+    }
+}
+
+// Rooms are sent as their code and their name, each prefixed by its length on one byte
+fn decode_rooms(mut bytes: &[u8]) -> io::Result<Vec<(String, String)>> {
+    let mut rooms = Vec::new();
+    while !bytes.is_empty() {
+        let code = check_code(next_text(&mut bytes)?)?;
+        let name = check_room_name(next_text(&mut bytes)?)?;
+        rooms.push((code, name));
+    }
+    Ok(rooms)
+}
+
+// Lines are sent as the number of bytes of their cells, followed by the row and the column of each
+// cell
+fn encode_lines(lines: &[Line]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for line in lines {
+        bytes.push(2 * line.len() as u8);
+        for &(row, column) in line {
+            bytes.extend([row as u8, column as u8]);
+        }
+    }
+    bytes
+}
+
+fn decode_lines(mut bytes: &[u8]) -> io::Result<Vec<Line>> {
+    let mut lines = Vec::new();
+    while !bytes.is_empty() {
+        let cells = next_text(&mut bytes)?;
+        let valid =
+            |cell: &[u8]| (cell[0] as usize) < BOARD_HEIGHT && (cell[1] as usize) < BOARD_WIDTH;
+        if cells.len() % 2 != 0 || !cells.chunks(2).all(valid) {
+            return Err(invalid_data(format!("invalid line: {:?}", cells)));
+        }
+        let line = cells
+            .chunks(2)
+            .map(|cell| (cell[0] as usize, cell[1] as usize))
+            .collect();
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+// Take a text prefixed by its length from the beginning of the bytes
+fn next_text<'a>(bytes: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    match **bytes {
+        [length, ref rest @ ..] if rest.len() >= length as usize => {
+            let (text, rest) = rest.split_at(length as usize);
+            *bytes = rest;
+            Ok(text)
+        }
+        _ => Err(invalid_data(String::from("truncated text"))),
+    }
+}
+
+// Durations are sent as a number of milliseconds on 4 bytes
+fn encode_duration(duration: Duration) -> [u8; 4] {
+    (duration.as_millis().min(u32::MAX as u128) as u32).to_be_bytes()
+}
+
+fn decode_duration(bytes: &[u8]) -> Duration {
+    Duration::from_millis(u32::from_be_bytes(bytes.try_into().unwrap()) as u64)
+}
+
+fn decode_token(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes.try_into().unwrap())
+}
+
+// Times are sent as a number of seconds since the Unix epoch on 8 bytes
+fn decode_time(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes.try_into().unwrap())
+}
+
+fn decode_id(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes.try_into().unwrap())
+}
+
+fn encode_time_control(time_control: TimeControl) -> Vec<u8> {
+    match time_control {
+        TimeControl::Unlimited => vec![0],
+        TimeControl::Increment(base, increment) => {
+            let mut bytes = vec![1];
+            bytes.extend(encode_duration(base));
+            bytes.extend(encode_duration(increment));
+            bytes
+        }
+        TimeControl::PerMove(time) => {
+            let mut bytes = vec![2];
+            bytes.extend(encode_duration(time));
+            bytes
+        }
+    }
+}
+
+fn decode_time_control(bytes: &[u8]) -> io::Result<TimeControl> {
+    Ok(match bytes {
+        [0] => TimeControl::Unlimited,
+        [1, durations @ ..] if durations.len() == 8 => TimeControl::Increment(
+            decode_duration(&durations[..4]),
+            decode_duration(&durations[4..]),
+        ),
+        [2, duration @ ..] if duration.len() == 4 => {
+            TimeControl::PerMove(decode_duration(duration))
+        }
+        _ => {
+            return Err(invalid_data(format!(
+                "bytes cannot be converted to a time control: {:?}",
+                bytes
+            )))
+        }
+    })
+}
+
+fn encode_termination(termination: Termination) -> u8 {
+    match termination {
+        Termination::Normal => 0,
+        Termination::TimeForfeit => 1,
+        Termination::Abandoned => 2,
+        Termination::Adjourned => 3,
+        Termination::Resignation => 4,
+        Termination::Agreement => 5,
+    }
+}
+
+fn decode_termination(byte: u8) -> io::Result<Termination> {
+    match byte {
+        0 => Ok(Termination::Normal),
+        1 => Ok(Termination::TimeForfeit),
+        2 => Ok(Termination::Abandoned),
+        3 => Ok(Termination::Adjourned),
+        4 => Ok(Termination::Resignation),
+        5 => Ok(Termination::Agreement),
+        _ => Err(invalid_data(format!(
+            "byte cannot be converted to a termination: {}",
+            byte
+        ))),
+    }
+}
@@ -0,0 +1,191 @@
+use super::Message;
+use std::{
+    io::{self, ErrorKind},
+    net,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+use tokio::{
+    io::{AsyncWriteExt, ReadBuf},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+
+// Connection to the server seen from a blocking client: a socket, or a channel to a server running
+// in the same process
+pub trait Transport: Send {
+    fn send(&mut self, message: Message) -> io::Result<()>;
+    fn receive(&mut self) -> io::Result<Message>;
+    // Other handle on the same connection, so that the messages can be received by another thread
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>>;
+}
+
+impl Transport for net::TcpStream {
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        message.write_to(self)
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        Message::read_from(self)
+    }
+
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(net::TcpStream::try_clone(self)?))
+    }
+}
+
+fn closed() -> io::Error {
+    io::Error::new(
+        ErrorKind::ConnectionAborted,
+        "the other end of the channel was dropped",
+    )
+}
+
+// End of an in-memory connection, for the tasks of the server. Messages are passed as they are,
+// without being encoded.
+pub struct ChannelEnd {
+    sender: UnboundedSender<Message>,
+    receiver: UnboundedReceiver<Message>,
+}
+
+impl ChannelEnd {
+    // Both ends of a new connection
+    pub fn pair() -> (Self, Self) {
+        let (sender_1, receiver_1) = mpsc::unbounded_channel();
+        let (sender_2, receiver_2) = mpsc::unbounded_channel();
+        (
+            Self {
+                sender: sender_1,
+                receiver: receiver_2,
+            },
+            Self {
+                sender: sender_2,
+                receiver: receiver_1,
+            },
+        )
+    }
+
+    pub fn send(&self, message: Message) -> io::Result<()> {
+        self.sender.send(message).map_err(|_| closed())
+    }
+
+    pub async fn receive(&mut self) -> io::Result<Message> {
+        self.receiver.recv().await.ok_or_else(closed)
+    }
+}
+
+// End of an in-memory connection, for a blocking client. It must not be used by the tasks of a
+// runtime.
+#[derive(Clone)]
+pub struct Channel {
+    sender: UnboundedSender<Message>,
+    receiver: Arc<Mutex<UnboundedReceiver<Message>>>,
+}
+
+impl From<ChannelEnd> for Channel {
+    fn from(end: ChannelEnd) -> Self {
+        Self {
+            sender: end.sender,
+            receiver: Arc::new(Mutex::new(end.receiver)),
+        }
+    }
+}
+
+impl Transport for Channel {
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        self.sender.send(message).map_err(|_| closed())
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        self.receiver
+            .lock()
+            .unwrap()
+            .blocking_recv()
+            .ok_or_else(closed)
+    }
+
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+// Connection to a player seen from the server
+pub enum Link {
+    Tcp(TcpStream),
+    Channel(ChannelEnd),
+}
+
+impl From<TcpStream> for Link {
+    fn from(stream: TcpStream) -> Self {
+        Link::Tcp(stream)
+    }
+}
+
+impl Link {
+    // Whether the other end closed the connection, checked without waiting and without consuming
+    // what it sent
+    pub fn closed(&self) -> bool {
+        match self {
+            Link::Tcp(stream) => {
+                let mut byte = [0; 1];
+                let mut context = Context::from_waker(Waker::noop());
+                matches!(
+                    stream.poll_peek(&mut context, &mut ReadBuf::new(&mut byte)),
+                    Poll::Ready(Ok(0) | Err(_))
+                )
+            }
+            Link::Channel(end) => end.sender.is_closed(),
+        }
+    }
+
+    // Separate the connection so that it can be read and written by different tasks
+    pub fn split(self) -> (LinkReader, LinkWriter) {
+        match self {
+            Link::Tcp(stream) => {
+                let (reader, writer) = stream.into_split();
+                (LinkReader::Tcp(reader), LinkWriter::Tcp(writer))
+            }
+            Link::Channel(ChannelEnd { sender, receiver }) => {
+                (LinkReader::Channel(receiver), LinkWriter::Channel(sender))
+            }
+        }
+    }
+}
+
+pub enum LinkReader {
+    Tcp(OwnedReadHalf),
+    Channel(UnboundedReceiver<Message>),
+}
+
+impl LinkReader {
+    pub async fn receive(&mut self) -> io::Result<Message> {
+        match self {
+            LinkReader::Tcp(reader) => Message::read_async(reader).await,
+            LinkReader::Channel(receiver) => receiver.recv().await.ok_or_else(closed),
+        }
+    }
+}
+
+pub enum LinkWriter {
+    Tcp(OwnedWriteHalf),
+    Channel(UnboundedSender<Message>),
+}
+
+impl LinkWriter {
+    pub async fn send(&mut self, message: &Message) -> io::Result<()> {
+        match self {
+            LinkWriter::Tcp(writer) => message.write_async(writer).await,
+            LinkWriter::Channel(sender) => sender.send(message.clone()).map_err(|_| closed()),
+        }
+    }
+
+    // Close a socket, the other end notices it when reading. Channels are closed when dropped.
+    pub async fn shutdown(&mut self) {
+        if let LinkWriter::Tcp(writer) = self {
+            let _ = writer.shutdown().await;
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use rand::Rng;
 use std::{
-    char, cmp, fmt, fs,
+    char, fmt, fs,
+    io::{self, ErrorKind},
     ops::{Index, IndexMut},
 };
 
@@ -8,11 +9,9 @@ use std::{
 pub const BOARD_HEIGHT: usize = 6;
 pub const BOARD_WIDTH: usize = 7;
 pub const ALIGN_TARGET: i32 = 4;
-const MAX_CHECK_LEN: usize = if BOARD_HEIGHT > BOARD_WIDTH {
-    BOARD_HEIGHT
-} else {
-    BOARD_WIDTH
-};
+
+// Directions of the lines: horizontal, vertical and both diagonals
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
 
 // Player enumeration and helpful functions
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -56,6 +55,9 @@ impl fmt::Display for Player {
     }
 }
 
+// Positions of aligned tokens, as (row, column) from the bottom left corner
+pub type Line = Vec<(usize, usize)>;
+
 // Enumeration for each cell, empty / red / yellow
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Cell {
@@ -64,8 +66,6 @@ pub enum Cell {
     Yellow,
 }
 
-type CheckSlice = [Cell; MAX_CHECK_LEN];
-
 // Get cell from player
 impl From<Player> for Cell {
     fn from(player: Player) -> Self {
@@ -106,17 +106,56 @@ impl Connect4 {
 
     // check if last move was a winning move
     pub fn check_winner(&self) -> Option<Player> {
-        let player = self.to_play.other();
-        let pos = self.last_move;
-        let winner = [(1, 0), (0, 1), (1, 1), (1, -1)]
+        DIRECTIONS
             .iter()
-            .map(|&direction| self.sub_board(pos, direction))
-            .any(|check_slice| Self::check_winner_list(player, check_slice));
-        if winner {
-            Some(player)
-        } else {
-            None
+            .any(|&direction| self.line_of_last_move(direction).1 >= ALIGN_TARGET as usize)
+            .then(|| self.to_play.other())
+    }
+
+    // Lines of at least four tokens completed by the last move, there are several if it completed
+    // more than one
+    pub fn winning_lines(&self) -> Vec<Line> {
+        DIRECTIONS
+            .iter()
+            .filter_map(|&(dx, dy)| {
+                let ((row, column), length) = self.line_of_last_move((dx, dy));
+                let line = (0..length as i32)
+                    .map(|step| {
+                        (
+                            (row as i32 + dy * step) as usize,
+                            (column as i32 + dx * step) as usize,
+                        )
+                    })
+                    .collect();
+                Some(line).filter(|_| length >= ALIGN_TARGET as usize)
+            })
+            .collect()
+    }
+
+    // Tokens aligned with the last move in the direction, including it, that its player played:
+    // the first one and their number
+    fn line_of_last_move(&self, (dx, dy): (i32, i32)) -> ((usize, usize), usize) {
+        let (row, column) = self.last_move;
+        let player = Cell::from(self.to_play.other());
+        if self[(row, column)] != player {
+            return ((row, column), 0);
         }
+        let aligned = |(dx, dy): (i32, i32)| {
+            (1..)
+                .map(|step| (row as i32 + dy * step, column as i32 + dx * step))
+                .take_while(|&(row, column)| {
+                    (0..BOARD_HEIGHT as i32).contains(&row)
+                        && (0..BOARD_WIDTH as i32).contains(&column)
+                        && self[(row as usize, column as usize)] == player
+                })
+                .count()
+        };
+        let (before, after) = (aligned((-dx, -dy)), aligned((dx, dy)));
+        let first = (
+            (row as i32 - dy * before as i32) as usize,
+            (column as i32 - dx * before as i32) as usize,
+        );
+        (first, before + 1 + after)
     }
 
     // Create an empty board to initialize a game
@@ -129,9 +168,22 @@ impl Connect4 {
         }
     }
 
+    // Create the board of a game from the list of the columns played so far, or None if a move
+    // is not valid or follows the end of the game
+    pub fn from_moves(moves: &[u8]) -> Option<Self> {
+        let mut game = Self::new();
+        for &column in moves {
+            if game.over() || !game.valid_action(column as usize) {
+                return None;
+            }
+            game.play(column as usize);
+        }
+        Some(game)
+    }
+
     // Check if the game is over (draw, full or win)
     pub fn over(&self) -> bool {
-        matches!(self.check_winner(), Some(_)) | self.check_full()
+        self.check_winner().is_some() | self.check_full()
     }
 
     // Play a move in the column input (consider that the column is valid)
@@ -144,7 +196,7 @@ impl Connect4 {
     }
 
     // Play a random possible move
-    pub fn play_random_move(&mut self) -> () {
+    pub fn play_random_move(&mut self) {
         let mut possible_moves: Vec<usize> = Vec::new();
         for column in 0..BOARD_WIDTH {
             if self.columns_height[column] < BOARD_HEIGHT {
@@ -156,7 +208,7 @@ impl Connect4 {
     }
 
     // Save a file for a recap of the game
-    pub fn save(&self, filename: String, history: Vec<u8>) -> () {
+    pub fn save(&self, filename: String, history: Vec<u8>) {
         // Played moves list for each player
         let mut red_moves: Vec<char> = Vec::new();
         let mut yellow_moves: Vec<char> = Vec::new();
@@ -178,7 +230,17 @@ impl Connect4 {
         let red_string: String = red_moves.iter().collect();
         let result_string = if self.over() {
             if let Some(winner) = self.check_winner() {
-                format!("Winner : {}", winner)
+                let cells: Vec<String> = self
+                    .winning_lines()
+                    .iter()
+                    .flatten()
+                    .map(|(row, column)| format!("({}, {})", column, row))
+                    .collect();
+                format!(
+                    "Winner : {}\nWinning line (column, row from the bottom): {}",
+                    winner,
+                    cells.join(" ")
+                )
             } else {
                 String::from("Draw")
             }
@@ -198,9 +260,46 @@ impl Connect4 {
         fs::write(filename, total_string).expect("Unable to write data.");
     }
 
+    // Read the moves of a game saved by save
+    pub fn load_moves(filename: &str) -> io::Result<Vec<u8>> {
+        let text = fs::read_to_string(filename)?;
+        let invalid = || io::Error::new(ErrorKind::InvalidData, "not a replay file");
+        let mut lines = text.lines();
+        let mut moves = |prefix: &str| {
+            let line = lines.next().filter(|line| line.starts_with(prefix));
+            let (_, moves) = line
+                .and_then(|line| line.split_once("):"))
+                .ok_or_else(invalid)?;
+            moves
+                .split(';')
+                .map(str::trim)
+                .filter(|action| !action.is_empty())
+                .map(|action| {
+                    action
+                        .parse()
+                        .ok()
+                        .filter(|&action| action < BOARD_WIDTH as u8)
+                })
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(invalid)
+        };
+        let (red, yellow) = (moves("Red moves")?, moves("Yellow moves")?);
+        if red.len() != yellow.len() && red.len() != yellow.len() + 1 {
+            return Err(invalid());
+        }
+        let mut history = Vec::new();
+        for (index, &action) in red.iter().enumerate() {
+            history.push(action);
+            history.extend(yellow.get(index));
+        }
+        // The moves must make a valid game
+        Self::from_moves(&history).ok_or_else(invalid)?;
+        Ok(history)
+    }
+
     // Verify if an action is valid (column in the board + the column is not full)
     pub fn valid_action(&self, column: usize) -> bool {
-        (column < BOARD_WIDTH) & (self.columns_height[column] < BOARD_HEIGHT)
+        (column < BOARD_WIDTH) && (self.columns_height[column] < BOARD_HEIGHT)
     }
 
     // Check if the coordinates are inside the board
@@ -218,56 +317,11 @@ impl Connect4 {
             );
         }
     }
+}
 
-    // Verify if there is 4 Cells aligned in a list
-    fn check_winner_list(player: Player, list: CheckSlice) -> bool {
-        let mut count = 0;
-        let player = Cell::from(player);
-        for &played in list.iter() {
-            if played == player {
-                count += 1;
-                if count == ALIGN_TARGET {
-                    return true;
-                }
-            } else {
-                count = 0;
-            }
-        }
-        false
-    }
-
-    // Get the slices for the row, the column or the diagonals for the last move
-    fn compute_indices((row, column): (i32, i32), (dx, dy): (i32, i32)) -> (usize, i32, i32) {
-        match (dx, dy) {
-            (1, 0) => (BOARD_WIDTH, row, 0),
-            (0, 1) => (BOARD_HEIGHT, 0, column),
-            _ => {
-                let (pos_row, neg_row) = if dy == 1 {
-                    (row, BOARD_HEIGHT as i32 - 1 - row)
-                } else {
-                    (BOARD_HEIGHT as i32 - 1 - row, row)
-                };
-                let mini = cmp::min(pos_row, column);
-                let maxi = cmp::min(neg_row, BOARD_WIDTH as i32 - 1 - column);
-                (
-                    1 + (mini + maxi) as usize,
-                    row as i32 - dy * mini as i32,
-                    (column - mini) as i32,
-                )
-            }
-        }
-    }
-
-    // Get the vector
-    fn sub_board(&self, (row, column): (usize, usize), (dx, dy): (i32, i32)) -> CheckSlice {
-        let (len, mut i, mut j) = Self::compute_indices((row as i32, column as i32), (dx, dy));
-        let mut check_slice = [Cell::Empty; MAX_CHECK_LEN];
-        for ind in 0..len {
-            check_slice[ind] = self[(i as usize, j as usize)];
-            i += dy;
-            j += dx;
-        }
-        check_slice
+impl Default for Connect4 {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -290,15 +344,21 @@ impl IndexMut<(usize, usize)> for Connect4 {
     }
 }
 
-// Display the connect4 board
+// Display the connect4 board, the tokens of the winning lines are between stars
 impl fmt::Display for Connect4 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let winning: Vec<(usize, usize)> = self.winning_lines().concat();
         let mut board_vec: Vec<char> = Vec::new();
         for row_number in (0..BOARD_HEIGHT).rev() {
             for column_number in 0..(BOARD_WIDTH) {
-                board_vec.push('|');
+                let border = if winning.contains(&(row_number, column_number)) {
+                    '*'
+                } else {
+                    '|'
+                };
+                board_vec.push(border);
                 board_vec.push(self[(row_number, column_number)].into());
-                board_vec.push('|');
+                board_vec.push(border);
             }
             board_vec.push('\n')
         }
@@ -1,6 +1,21 @@
+#![allow(clippy::module_inception)]
+
 pub mod ai;
-pub mod communication;
 pub mod game_logic;
 
+// The networking, the terminal and the files are not available in the browser, which only runs
+// the game logic and the ai
+#[cfg(not(target_arch = "wasm32"))]
+pub mod archive;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod clock;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod communication;
+
+#[cfg(not(target_arch = "wasm32"))]
 pub mod client;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod server;
+
+#[cfg(target_arch = "wasm32")]
+pub mod web;
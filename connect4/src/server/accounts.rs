@@ -0,0 +1,236 @@
+use super::lobby::BOT_PREFIX;
+use crate::game_logic::Player::{self, *};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use rand::Rng;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+};
+use tokio::task;
+use tracing::error;
+
+// Rating of a new account
+pub const INITIAL_RATING: f64 = 1500.0;
+
+// Elo coefficients: ratings of new players move faster until they have played enough games
+const PROVISIONAL_K_FACTOR: f64 = 40.0;
+const K_FACTOR: f64 = 20.0;
+const PROVISIONAL_GAMES: u32 = 30;
+
+struct Account {
+    password_hash: String,
+    rating: f64,
+    games: u32,
+}
+
+struct Players {
+    writer: Option<Sender<String>>, // accounts are only kept in memory without a file
+    accounts: HashMap<String, Account>,
+    in_use: HashSet<String>, // accounts logged in to by a connection
+}
+
+// Account used by a connection, which can log in to it again once this is dropped
+pub struct InUse {
+    accounts: Accounts,
+    name: String,
+}
+
+impl Drop for InUse {
+    fn drop(&mut self) {
+        self.accounts.0.lock().unwrap().in_use.remove(&self.name);
+    }
+}
+
+// Accounts of the players, saved in a text file with one line per account: name, rating, number
+// of rated games and password hash, separated by tabs
+#[derive(Clone)]
+pub struct Accounts(Arc<Mutex<Players>>);
+
+impl Accounts {
+    // Read the accounts from the file, which is created when the first account is registered. It
+    // must be called by a task of a runtime, in which the file is written.
+    pub fn load(file: Option<PathBuf>) -> Self {
+        let mut accounts = HashMap::new();
+        if let Some(content) = file.as_ref().and_then(|file| fs::read_to_string(file).ok()) {
+            for line in content.lines().filter(|line| !line.is_empty()) {
+                let (name, account) = parse_account(line)
+                    .unwrap_or_else(|| panic!("Unvalid line in the accounts file: {}", line));
+                accounts.insert(name, account);
+            }
+        }
+        // Writing to the disk blocks, so it is done by a thread for blocking tasks
+        let writer = file.map(|file| {
+            let (sender, receiver) = mpsc::channel();
+            task::spawn_blocking(move || write_accounts(file, receiver));
+            sender
+        });
+        Self(Arc::new(Mutex::new(Players {
+            writer,
+            accounts,
+            in_use: HashSet::new(),
+        })))
+    }
+
+    // Create an account and return its rating, or None if the name is taken or reserved for bots
+    pub async fn register(&self, name: String, password: String) -> Option<f64> {
+        if name.starts_with(BOT_PREFIX) || self.0.lock().unwrap().accounts.contains_key(&name) {
+            return None;
+        }
+        // Hashing is slow on purpose, so it is done outside of the tasks of the runtime
+        let password_hash = task::spawn_blocking(move || hash(&password)).await.unwrap();
+        let mut players = self.0.lock().unwrap();
+        if players.accounts.contains_key(&name) {
+            return None;
+        }
+        let account = Account {
+            password_hash,
+            rating: INITIAL_RATING,
+            games: 0,
+        };
+        players.accounts.insert(name, account);
+        players.save();
+        Some(INITIAL_RATING)
+    }
+
+    // Check the password of an account and return its rating
+    pub async fn login(&self, name: &str, password: String) -> Option<f64> {
+        let password_hash = self
+            .0
+            .lock()
+            .unwrap()
+            .accounts
+            .get(name)?
+            .password_hash
+            .clone();
+        let valid = task::spawn_blocking(move || verify(&password, &password_hash))
+            .await
+            .unwrap();
+        if valid {
+            self.rating(name)
+        } else {
+            None
+        }
+    }
+
+    // Mark an account as used by a connection until the result is dropped, returns None if it
+    // already is
+    pub fn claim(&self, name: &str) -> Option<InUse> {
+        if !self.0.lock().unwrap().in_use.insert(name.to_owned()) {
+            return None;
+        }
+        Some(InUse {
+            accounts: self.clone(),
+            name: name.to_owned(),
+        })
+    }
+
+    pub fn rating(&self, name: &str) -> Option<f64> {
+        Some(self.0.lock().unwrap().accounts.get(name)?.rating)
+    }
+
+    // Update the ratings of red and yellow after a game, and return them
+    pub fn record(&self, (red, yellow): (&str, &str), winner: Option<Player>) -> (f64, f64) {
+        let mut players = self.0.lock().unwrap();
+        let (red_rating, yellow_rating) = (
+            players.accounts[red].rating,
+            players.accounts[yellow].rating,
+        );
+        let score = match winner {
+            Some(Red) => 1.0,
+            Some(Yellow) => 0.0,
+            None => 0.5,
+        };
+        let expected = 1.0 / (1.0 + 10f64.powf((yellow_rating - red_rating) / 400.0));
+        for (name, change) in [(red, score - expected), (yellow, expected - score)] {
+            let account = players.accounts.get_mut(name).unwrap();
+            let k_factor = if account.games < PROVISIONAL_GAMES {
+                PROVISIONAL_K_FACTOR
+            } else {
+                K_FACTOR
+            };
+            account.rating = (account.rating + k_factor * change).max(0.0);
+            account.games += 1;
+        }
+        players.save();
+        (
+            players.accounts[red].rating,
+            players.accounts[yellow].rating,
+        )
+    }
+}
+
+impl Players {
+    // Hand the content of the file to the writer
+    fn save(&self) {
+        let writer = match &self.writer {
+            Some(writer) => writer,
+            None => return,
+        };
+        let mut names: Vec<_> = self.accounts.keys().collect();
+        names.sort();
+        let content: String = names
+            .into_iter()
+            .map(|name| {
+                let account = &self.accounts[name];
+                format!(
+                    "{}\t{:.1}\t{}\t{}\n",
+                    name, account.rating, account.games, account.password_hash
+                )
+            })
+            .collect();
+        let _ = writer.send(content);
+    }
+}
+
+// Write the accounts until they are dropped, only the last content received matters. It is
+// written in a temporary file first so that the file is never left half written.
+fn write_accounts(file: PathBuf, contents: Receiver<String>) {
+    let temporary = file.with_extension("tmp");
+    while let Ok(content) = contents.recv() {
+        let content = contents.try_iter().last().unwrap_or(content);
+        if let Err(error) =
+            fs::write(&temporary, content).and_then(|_| fs::rename(&temporary, &file))
+        {
+            error!(%error, "unable to save the accounts");
+        }
+    }
+}
+
+fn parse_account(line: &str) -> Option<(String, Account)> {
+    let mut fields = line.split('\t');
+    let name = fields.next()?.to_owned();
+    let rating = fields.next()?.parse().ok()?;
+    let games = fields.next()?.parse().ok()?;
+    let password_hash = fields.next()?.to_owned();
+    let account = Account {
+        password_hash,
+        rating,
+        games,
+    };
+    Some((name, account))
+}
+
+fn hash(password: &str) -> String {
+    let salt = SaltString::encode_b64(&rand::thread_rng().gen::<[u8; 16]>()).unwrap();
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+fn verify(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(password_hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
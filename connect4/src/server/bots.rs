@@ -0,0 +1,80 @@
+use super::metrics::Metrics;
+use crate::{
+    ai,
+    communication::{
+        transport::{ChannelEnd, Link},
+        Message::*,
+    },
+    game_logic::Connect4,
+};
+use std::sync::Arc;
+use tokio::{sync::Semaphore, task, time::Instant};
+
+// Bots of the server, which play with the ai at a depth depending on their level. Their searches
+// run on a bounded number of threads so that they cannot slow down the games between players.
+#[derive(Clone)]
+pub struct Bots {
+    depths: Arc<Vec<usize>>, // depth of the search of each level, starting from level 1
+    workers: Arc<Semaphore>,
+    metrics: Metrics,
+}
+
+impl Bots {
+    pub fn new(depths: Vec<usize>, workers: usize, metrics: Metrics) -> Self {
+        Self {
+            depths: Arc::new(depths),
+            workers: Arc::new(Semaphore::new(workers)),
+            metrics,
+        }
+    }
+
+    // Start a bot of the given level and return the connection to it, if there is such a level
+    pub fn spawn(&self, level: u8) -> Option<Link> {
+        let depth = *self.depths.get((level as usize).checked_sub(1)?)?;
+        Some(self.spawn_with_depth(depth))
+    }
+
+    // Start a bot searching at the given depth and return the connection to it
+    pub fn spawn_with_depth(&self, depth: usize) -> Link {
+        let (link, bot_end) = ChannelEnd::pair();
+        tokio::spawn(play(
+            bot_end,
+            depth,
+            Arc::clone(&self.workers),
+            self.metrics.clone(),
+        ));
+        Link::Channel(link)
+    }
+}
+
+// Play games as a client of the server would, until the connection is closed. Bots always accept a
+// rematch.
+async fn play(mut link: ChannelEnd, depth: usize, workers: Arc<Semaphore>, metrics: Metrics) {
+    let mut game = Connect4::new();
+    loop {
+        match link.receive().await {
+            Ok(Play(_)) => {
+                let start = Instant::now();
+                let permit = workers.acquire().await.unwrap();
+                let position = game.clone();
+                let action = task::spawn_blocking(move || ai::action(position, depth))
+                    .await
+                    .unwrap();
+                drop(permit);
+                metrics.bot_move(start.elapsed());
+                if link.send(Action(action as u8)).is_err() {
+                    return;
+                }
+            }
+            Ok(Hello(..)) => game = Connect4::new(),
+            Ok(ValidAction(action)) => game.play(action as usize),
+            Ok(OfferRematch) => {
+                if link.send(Rematch(true)).is_err() {
+                    return;
+                }
+            }
+            Err(_) => return,
+            Ok(_) => (),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use super::server::{HintPolicy, MAX_SERIES_LENGTH};
+use serde::Deserialize;
+use std::{
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+// Settings of the server read from a TOML file. Every value is optional: the options of the
+// command line override the values of the file, which override the default values.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub network: NetworkConfig,
+    pub limits: LimitsConfig,
+    pub games: GamesConfig,
+    pub bots: BotsConfig,
+    pub files: FilesConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub ip: Option<String>,
+    pub port: Option<u16>,
+    pub metrics_ip: Option<String>,
+    pub metrics_port: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub max_games: Option<usize>,
+    pub max_connections: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+    pub idle_timeout: Option<f64>,
+}
+
+// Times are in seconds
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamesConfig {
+    pub time: Option<f64>,
+    pub increment: Option<f64>,
+    pub move_time: Option<f64>,
+    pub grace: Option<f64>,
+    pub series: Option<usize>,
+    pub hints: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotsConfig {
+    pub depths: Option<Vec<usize>>,
+    pub workers: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+    pub accounts: Option<PathBuf>,
+    pub archive: Option<PathBuf>,
+}
+
+impl Config {
+    // Read and check a configuration file, returns all the errors found in it
+    pub fn load(path: &Path) -> Result<Self, Vec<String>> {
+        let content = fs::read_to_string(path).map_err(|error| vec![error.to_string()])?;
+        let config: Self = toml::from_str(&content).map_err(|error| vec![error.to_string()])?;
+        let errors = config.errors();
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut check = |valid: bool, key: &str, expected: &str| {
+            if !valid {
+                errors.push(format!(
+                    "Unvalid value for {}. It should be {}.",
+                    key, expected
+                ));
+            }
+        };
+        let network = &self.network;
+        for (ip, key) in [
+            (&network.ip, "network.ip"),
+            (&network.metrics_ip, "network.metrics_ip"),
+        ] {
+            let valid = ip.as_ref().is_none_or(|ip| ip.parse::<IpAddr>().is_ok());
+            check(valid, key, "an IP address");
+        }
+        let limits = &self.limits;
+        for (limit, key) in [
+            (limits.max_games, "limits.max_games"),
+            (limits.max_connections, "limits.max_connections"),
+            (
+                limits.max_connections_per_ip,
+                "limits.max_connections_per_ip",
+            ),
+        ] {
+            check(limit != Some(0), key, "a positive integer");
+        }
+        let games = &self.games;
+        for (time, key) in [
+            (limits.idle_timeout, "limits.idle_timeout"),
+            (games.time, "games.time"),
+            (games.move_time, "games.move_time"),
+        ] {
+            check(
+                time.is_none_or(|time| positive_seconds(time).is_some()),
+                key,
+                "a positive number of seconds",
+            );
+        }
+        for (time, key) in [
+            (games.increment, "games.increment"),
+            (games.grace, "games.grace"),
+        ] {
+            check(
+                time.is_none_or(|time| seconds(time).is_some()),
+                key,
+                "a number of seconds, possibly 0",
+            );
+        }
+        check(
+            games.time.is_none() || games.move_time.is_none(),
+            "games.move_time",
+            "absent when games.time is set",
+        );
+        check(
+            games.increment.is_none() || games.time.is_some(),
+            "games.increment",
+            "absent when games.time is not set",
+        );
+        check(
+            games
+                .series
+                .is_none_or(|series| (1..=MAX_SERIES_LENGTH).contains(&series)),
+            "games.series",
+            &format!("an integer between 1 and {}", MAX_SERIES_LENGTH),
+        );
+        check(
+            games
+                .hints
+                .as_ref()
+                .is_none_or(|hints| HintPolicy::from_name(hints).is_some()),
+            "games.hints",
+            "\"always\", \"unrated\" or \"never\"",
+        );
+        let bots = &self.bots;
+        check(
+            bots.depths
+                .as_ref()
+                .is_none_or(|depths| !depths.is_empty() && !depths.contains(&0)),
+            "bots.depths",
+            "a non-empty list of positive integers",
+        );
+        check(
+            bots.workers != Some(0),
+            "bots.workers",
+            "a positive integer",
+        );
+        errors
+    }
+}
+
+// Duration of a number of seconds read from the file, if it is valid
+pub fn seconds(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+// Same for the times which cannot be 0
+pub fn positive_seconds(seconds: f64) -> Option<Duration> {
+    self::seconds(seconds).filter(|duration| !duration.is_zero())
+}
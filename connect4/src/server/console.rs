@@ -0,0 +1,303 @@
+use super::{
+    lobby::turn_away,
+    server::{HintPolicy, Settings, Shared, MAX_SERIES_LENGTH},
+    session::{Control, Event},
+};
+use crate::{archive, clock::TimeControl, communication::MAX_CHAT_LENGTH, game_logic::Player::*};
+use std::{collections::HashSet, future, time::Duration};
+use tokio::{
+    io::{self, AsyncBufReadExt, BufReader},
+    time::{self, Instant},
+};
+
+// Time between two checks of the games still running while shutting down
+const SHUTDOWN_INTERVAL: Duration = Duration::from_secs(1);
+
+const HELP: &str = "\
+Commands:
+  games                      list the running games
+  players                    list the connected players
+  kick <name>                make a player lose their game, or remove them from the lobby
+  kick <game id> <color>     make the player of a game with this color lose it
+  abort <game id>            stop a game without a result, it is not archived
+  notice <text>              send a notice to the players and spectators of all the games
+  set time <seconds>|none    change the time control of the next games
+  set increment <seconds>
+  set movetime <seconds>
+  set grace <seconds>        change the time given to disconnected players to come back
+  set maxgames <n>|none      change the maximum number of games played at the same time
+  set maxconnections <n>|none
+                             change the maximum number of connections, in total and
+  set perip <n>|none         from a single address
+  set idle <seconds>|none    change the time a player may wait for an opponent or a move
+  set series <games>         change the length of the next series
+  set hints always|unrated|never
+                             change the games in which the players may ask for hints
+  shutdown [seconds]         stop accepting players and wait for the running games to end,
+                             adjourning the ones still running after the given time
+  help                       show this message";
+
+// Read the commands of the administrator on the standard input. Returns the time after which the
+// running games are adjourned once the administrator asked to shut down the server.
+pub async fn run(shared: Shared) -> Option<Duration> {
+    let mut lines = BufReader::new(io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let line = line.trim();
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        match command {
+            "" => (),
+            "games" => list_games(&shared),
+            "players" => list_players(&shared),
+            "kick" => kick(&shared, argument).await,
+            "abort" => match argument.parse() {
+                Ok(id) => control(&shared, id, Control::Abort).await,
+                Err(_) => println!("Usage: abort <game id>"),
+            },
+            "notice" if !argument.is_empty() && argument.len() <= MAX_CHAT_LENGTH => {
+                for (events, _) in shared.registry.games() {
+                    let _ = events
+                        .send(Event::Control(Control::Notice(argument.to_owned())))
+                        .await;
+                }
+            }
+            "notice" => println!(
+                "Notices must contain between 1 and {} bytes.",
+                MAX_CHAT_LENGTH
+            ),
+            "set" => set(&shared, argument),
+            "shutdown" if argument.is_empty() => return None,
+            "shutdown" => match argument.parse() {
+                Ok(seconds) => return Some(Duration::from_secs(seconds)),
+                Err(_) => println!("Usage: shutdown [seconds]"),
+            },
+            "help" => println!("{}", HELP),
+            _ => println!("Unknown command, type help for the list of commands."),
+        }
+    }
+    // Without a console, the server runs until it is killed
+    future::pending().await
+}
+
+fn list_games(shared: &Shared) {
+    let games = shared.registry.games();
+    if games.is_empty() {
+        println!("No game is running.");
+    }
+    for (_, game) in games {
+        println!(
+            "Game {}: {} (red) vs {} (yellow), started {}",
+            game.id,
+            display_name(&game.players.0),
+            display_name(&game.players.1),
+            archive::format_time(game.start)
+        );
+    }
+}
+
+fn list_players(shared: &Shared) {
+    let mut players = Vec::new();
+    for (_, game) in shared.registry.games() {
+        players.push((game.players.0, format!("red in game {}", game.id)));
+        players.push((game.players.1, format!("yellow in game {}", game.id)));
+    }
+    players.extend(shared.lobby.waiting());
+    for name in shared.registrations.names() {
+        players.push((name, String::from("registered to the tournament")));
+    }
+    if players.is_empty() {
+        println!("No player is connected.");
+    }
+    for (name, place) in players {
+        println!("{}: {}", display_name(&name), place);
+    }
+}
+
+fn display_name(name: &str) -> &str {
+    if name.is_empty() {
+        "guest"
+    } else {
+        name
+    }
+}
+
+// Send a command to the task of a game
+async fn control(shared: &Shared, id: u32, control: Control) {
+    match shared.registry.game(id) {
+        Some(events) => {
+            let _ = events.send(Event::Control(control)).await;
+        }
+        None => println!("There is no running game {}.", id),
+    }
+}
+
+async fn kick(shared: &Shared, argument: &str) {
+    let arguments: Vec<_> = argument.split_whitespace().collect();
+    match arguments[..] {
+        [id, color] => {
+            let color = match color {
+                "red" => Red,
+                "yellow" => Yellow,
+                _ => return println!("The color should be red or yellow."),
+            };
+            match id.parse() {
+                Ok(id) => control(shared, id, Control::Kick(color)).await,
+                Err(_) => println!("Usage: kick <game id> <color>"),
+            }
+        }
+        [name] => {
+            let mut found = false;
+            for (events, game) in shared.registry.games() {
+                for (player, color) in [(&game.players.0, Red), (&game.players.1, Yellow)] {
+                    if player == name {
+                        let _ = events.send(Event::Control(Control::Kick(color))).await;
+                        found = true;
+                    }
+                }
+            }
+            let waiting = shared.lobby.remove(name);
+            found |= !waiting.is_empty();
+            turn_away(waiting, "You were removed from the lobby by the server.").await;
+            if !found {
+                println!("{} is neither playing nor waiting for an opponent.", name);
+            }
+        }
+        _ => println!("Usage: kick <name> or kick <game id> <color>"),
+    }
+}
+
+fn parse_seconds(value: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(value.parse().ok()?).ok()
+}
+
+// Limit given as a positive integer, or none to remove it
+fn parse_limit(value: &str) -> Option<Option<usize>> {
+    match value {
+        "none" => Some(None),
+        _ => value.parse().ok().filter(|&limit| limit > 0).map(Some),
+    }
+}
+
+// Change a setting for the next games, the running ones keep theirs
+fn set(shared: &Shared, argument: &str) {
+    let (name, value) = argument.split_once(' ').unwrap_or((argument, ""));
+    let value = value.trim();
+    let mut settings = shared.settings.write().unwrap();
+    let Settings {
+        time_control,
+        grace_period,
+        series_length,
+        hints,
+        limits,
+        ..
+    } = &mut *settings;
+    let changed = match (name, parse_seconds(value)) {
+        ("time", _) if value == "none" => {
+            *time_control = TimeControl::Unlimited;
+            true
+        }
+        ("time", Some(base)) if !base.is_zero() => {
+            let increment = match *time_control {
+                TimeControl::Increment(_, increment) => increment,
+                _ => Duration::ZERO,
+            };
+            *time_control = TimeControl::Increment(base, increment);
+            true
+        }
+        ("increment", Some(increment)) => match *time_control {
+            TimeControl::Increment(base, _) => {
+                *time_control = TimeControl::Increment(base, increment);
+                true
+            }
+            _ => {
+                println!("Set a time first, increments only apply to it.");
+                return;
+            }
+        },
+        ("movetime", Some(time)) if !time.is_zero() => {
+            *time_control = TimeControl::PerMove(time);
+            true
+        }
+        ("grace", Some(grace)) => {
+            *grace_period = grace;
+            true
+        }
+        ("idle", _) if value == "none" => {
+            limits.idle_timeout = None;
+            true
+        }
+        ("idle", Some(timeout)) if !timeout.is_zero() => {
+            limits.idle_timeout = Some(timeout);
+            true
+        }
+        ("maxgames" | "maxconnections" | "perip", _) => match parse_limit(value) {
+            Some(limit) => {
+                *match name {
+                    "maxgames" => &mut limits.max_games,
+                    "maxconnections" => &mut limits.max_connections,
+                    _ => &mut limits.max_connections_per_ip,
+                } = limit;
+                true
+            }
+            None => false,
+        },
+        ("series", _) => match value.parse() {
+            Ok(length) if (1..=MAX_SERIES_LENGTH).contains(&length) => {
+                *series_length = length;
+                true
+            }
+            _ => false,
+        },
+        ("hints", _) => match HintPolicy::from_name(value) {
+            Some(policy) => {
+                *hints = policy;
+                true
+            }
+            None => false,
+        },
+        _ => false,
+    };
+    if changed {
+        println!("Done, the running games keep their settings.");
+    } else {
+        println!("Unknown setting or unvalid value, type help for the list of commands.");
+    }
+}
+
+// Stop the games from starting and wait for the running ones to end. The games still running
+// after the given time are adjourned.
+pub async fn shut_down(shared: &Shared, adjourn_after: Option<Duration>) {
+    shared.registry.close();
+    shared.registrations.close();
+    turn_away(shared.lobby.clear(), "The server is shutting down.").await;
+    // No rematch is played, the offers end right away
+    for prompt in shared.registry.prompts() {
+        let _ = prompt.send(Event::Control(Control::Adjourn)).await;
+    }
+    let deadline = adjourn_after.map(|delay| Instant::now() + delay);
+    // Games starting after the deadline, such as the next game of a series, are adjourned too
+    let mut adjourned = HashSet::new();
+    let mut running = 0;
+    loop {
+        let games = shared.registry.games();
+        if games.is_empty() && shared.registry.prompts().is_empty() {
+            println!("All the games are over, the server stops.");
+            return;
+        }
+        if games.len() != running {
+            running = games.len();
+            println!(
+                "Games still running: {}, waiting for them to end...",
+                running
+            );
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            for (events, game) in games {
+                if adjourned.insert(game.id) {
+                    let _ = events.send(Event::Control(Control::Adjourn)).await;
+                }
+            }
+        }
+        time::sleep(SHUTDOWN_INTERVAL).await;
+    }
+}
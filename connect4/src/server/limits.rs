@@ -0,0 +1,120 @@
+use super::{accounts::InUse, server::Settings};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+// Resources a server accepts to use, without limit by default
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Limits {
+    pub max_games: Option<usize>, // games played at the same time, tournaments excepted
+    pub max_connections: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+    pub idle_timeout: Option<Duration>, // time a player may wait for an opponent or think on a move
+}
+
+#[derive(Default)]
+struct Connections {
+    total: usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+// Counts of the connections and games of the server, which refuses new ones beyond its limits. The
+// limits are read from the settings, which the administrator may change.
+#[derive(Clone)]
+pub struct Capacity {
+    settings: Arc<RwLock<Settings>>,
+    connections: Arc<Mutex<Connections>>,
+    games: Arc<Mutex<usize>>,
+}
+
+// Connection counted by the server until it is dropped
+pub struct Slot {
+    connections: Arc<Mutex<Connections>>,
+    ip: IpAddr,
+    account: Option<InUse>, // account logged in to by the connection
+}
+
+// Game counted by the server until it is dropped
+pub struct GameSlot {
+    games: Arc<Mutex<usize>>,
+}
+
+fn below(count: usize, limit: Option<usize>) -> bool {
+    limit.is_none_or(|limit| count < limit)
+}
+
+impl Capacity {
+    pub fn new(settings: Arc<RwLock<Settings>>) -> Self {
+        Self {
+            settings,
+            connections: Arc::default(),
+            games: Arc::default(),
+        }
+    }
+
+    fn limits(&self) -> Limits {
+        self.settings.read().unwrap().limits.clone()
+    }
+
+    // Count a new connection from the given address, if the limits allow it
+    pub fn connect(&self, ip: IpAddr) -> Option<Slot> {
+        let limits = self.limits();
+        let mut connections = self.connections.lock().unwrap();
+        let from_ip = connections.per_ip.get(&ip).copied().unwrap_or(0);
+        if !below(connections.total, limits.max_connections)
+            || !below(from_ip, limits.max_connections_per_ip)
+        {
+            return None;
+        }
+        connections.total += 1;
+        connections.per_ip.insert(ip, from_ip + 1);
+        Some(Slot {
+            connections: Arc::clone(&self.connections),
+            ip,
+            account: None,
+        })
+    }
+
+    // Reserve a game until the slot is dropped, if the limit allows it. Lowering the limit does
+    // not stop the running games.
+    pub fn start_game(&self) -> Option<GameSlot> {
+        let max_games = self.limits().max_games;
+        let mut games = self.games.lock().unwrap();
+        if !below(*games, max_games) {
+            return None;
+        }
+        *games += 1;
+        Some(GameSlot {
+            games: Arc::clone(&self.games),
+        })
+    }
+}
+
+impl Slot {
+    // Keep the account in use as long as the connection is counted
+    pub fn log_in(&mut self, account: InUse) {
+        self.account = Some(account);
+    }
+}
+
+impl Drop for GameSlot {
+    fn drop(&mut self) {
+        *self.games.lock().unwrap() -= 1;
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut connections = self.connections.lock().unwrap();
+        connections.total -= 1;
+        if let Some(count) = connections.per_ip.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.per_ip.remove(&self.ip);
+            }
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use super::{accounts::INITIAL_RATING, limits::Slot};
+use crate::communication::{transport::Link, Message::Notice};
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{net::TcpStream, time::Instant};
+
+// Characters of the invite codes, without the ones which are easily confused (0/O, 1/I)
+const CODE_CHARACTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 6;
+
+// Prefix of the names of the bots in the archive, which accounts cannot use
+pub const BOT_PREFIX: &str = "bot-";
+
+// Difference of ratings accepted between two players of a quick match, which grows with the time
+// spent waiting so that everyone eventually finds an opponent
+const RATING_RANGE: f64 = 100.0;
+const RATING_RANGE_PER_SECOND: f64 = 20.0;
+
+// Connection of a player looking for an opponent, with the name of their account if they logged
+// in. Guests are matched as if they had the initial rating. Bots of the server have a level.
+// Connections to a socket are counted by the server while they hold their slot.
+pub struct Entrant {
+    pub link: Link,
+    pub account: Option<String>,
+    pub rating: f64,
+    pub bot_level: Option<u8>,
+    pub slot: Option<Slot>,
+}
+
+impl Entrant {
+    pub fn new(stream: TcpStream, slot: Slot, account: Option<(String, f64)>) -> Self {
+        let (account, rating) = match account {
+            Some((name, rating)) => (Some(name), rating),
+            None => (None, INITIAL_RATING),
+        };
+        Self {
+            link: stream.into(),
+            account,
+            rating,
+            bot_level: None,
+            slot: Some(slot),
+        }
+    }
+
+    // Player of a local game, who has no account
+    pub fn local(link: Link) -> Self {
+        Self {
+            link,
+            account: None,
+            rating: INITIAL_RATING,
+            bot_level: None,
+            slot: None,
+        }
+    }
+
+    pub fn bot(link: Link, level: u8) -> Self {
+        Self {
+            link,
+            account: None,
+            rating: INITIAL_RATING,
+            bot_level: Some(level),
+            slot: None,
+        }
+    }
+
+    // Players logged in to the same account never play each other
+    fn same_account(&self, other: &Entrant) -> bool {
+        self.account.is_some() && self.account == other.account
+    }
+
+    // Name of the player in the archive: their account, the level of the bot or nothing
+    pub fn archive_name(&self) -> String {
+        match (&self.account, self.bot_level) {
+            (Some(account), _) => account.clone(),
+            (None, Some(level)) => format!("{}{}", BOT_PREFIX, level),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+// Player of the quick-match queue and when they started waiting
+struct Candidate {
+    entrant: Entrant,
+    since: Instant,
+}
+
+impl Candidate {
+    // Largest difference of ratings accepted by the player
+    fn range(&self) -> f64 {
+        RATING_RANGE + RATING_RANGE_PER_SECOND * self.since.elapsed().as_secs_f64()
+    }
+}
+
+// Room created by a player, waiting for an opponent. Private rooms are not listed. The host is
+// missing while they are being sent the invite code.
+struct Room {
+    name: String,
+    private: bool,
+    host: Option<Entrant>,
+    since: Instant,
+}
+
+#[derive(Default)]
+struct Waiting {
+    queue: Vec<Candidate>,
+    rooms: HashMap<String, Room>,
+}
+
+// Players waiting for an opponent, either in the quick-match queue or in a room
+#[derive(Clone, Default)]
+pub struct Lobby(Arc<Mutex<Waiting>>);
+
+impl Lobby {
+    // Pair the player with the waiting player of the closest rating who accepts them, or make them
+    // wait
+    pub fn quick_match(&self, player: Entrant) -> Option<(Entrant, Entrant)> {
+        let mut waiting = self.0.lock().unwrap();
+        let closest = waiting
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| {
+                !other.entrant.same_account(&player) && !other.entrant.link.closed()
+            })
+            .map(|(index, other)| (index, (other.entrant.rating - player.rating).abs()))
+            .filter(|&(index, difference)| difference <= waiting.queue[index].range())
+            .min_by(|(_, difference), (_, other)| difference.total_cmp(other));
+        match closest {
+            Some((index, _)) => Some((waiting.queue.remove(index).entrant, player)),
+            None => {
+                waiting.queue.push(Candidate {
+                    entrant: player,
+                    since: Instant::now(),
+                });
+                None
+            }
+        }
+    }
+
+    // Pair the players of the queue whose range grew enough for them to accept each other, the
+    // ones who have been waiting for the longest first
+    pub fn match_waiting(&self) -> Vec<(Entrant, Entrant)> {
+        let mut waiting = self.0.lock().unwrap();
+        let mut pairs = Vec::new();
+        let mut first = 0;
+        while first < waiting.queue.len() {
+            let candidate = &waiting.queue[first];
+            let closest = waiting
+                .queue
+                .iter()
+                .enumerate()
+                .skip(first + 1)
+                .filter(|(_, other)| !other.entrant.same_account(&candidate.entrant))
+                .map(|(index, other)| {
+                    let difference = (other.entrant.rating - candidate.entrant.rating).abs();
+                    (index, difference, candidate.range().max(other.range()))
+                })
+                .filter(|&(_, difference, range)| difference <= range)
+                .min_by(|(_, difference, _), (_, other, _)| difference.total_cmp(other));
+            match closest {
+                Some((index, ..)) => {
+                    let other = waiting.queue.remove(index).entrant;
+                    pairs.push((waiting.queue.remove(first).entrant, other));
+                }
+                None => first += 1,
+            }
+        }
+        pairs
+    }
+
+    // Reserve a room and return its invite code, the host is added with `open_room`
+    pub fn create_room(&self, name: String, private: bool) -> String {
+        let mut waiting = self.0.lock().unwrap();
+        let code = loop {
+            let code = new_code();
+            if !waiting.rooms.contains_key(&code) {
+                break code;
+            }
+        };
+        let room = Room {
+            name,
+            private,
+            host: None,
+            since: Instant::now(),
+        };
+        waiting.rooms.insert(code.clone(), room);
+        code
+    }
+
+    // Let the host of a room wait in it for an opponent
+    pub fn open_room(&self, code: &str, host: Entrant) {
+        if let Some(room) = self.0.lock().unwrap().rooms.get_mut(code) {
+            room.host = Some(host);
+        }
+    }
+
+    // Remove a room whose host left before it was opened
+    pub fn close_room(&self, code: &str) {
+        self.0.lock().unwrap().rooms.remove(code);
+    }
+
+    // Pair the player with the host of the room, gives the connection back if there is no such room
+    // or if the host is logged in to the same account
+    pub fn join_room(
+        &self,
+        code: &str,
+        player: Entrant,
+    ) -> Result<(Entrant, Entrant), Box<Entrant>> {
+        let mut waiting = self.0.lock().unwrap();
+        let code = code.to_uppercase();
+        let room = waiting.rooms.get(&code);
+        match room.and_then(|room| room.host.as_ref()) {
+            // The host left before anyone joined the room
+            Some(host) if host.link.closed() => {
+                waiting.rooms.remove(&code);
+                Err(Box::new(player))
+            }
+            Some(host) if !host.same_account(&player) => {
+                let host = waiting.rooms.remove(&code).unwrap().host.unwrap();
+                Ok((host, player))
+            }
+            _ => Err(Box::new(player)),
+        }
+    }
+
+    // Names in the archive of the waiting players and where they wait
+    pub fn waiting(&self) -> Vec<(String, String)> {
+        let waiting = self.0.lock().unwrap();
+        let queue = waiting.queue.iter().map(|candidate| {
+            (
+                candidate.entrant.archive_name(),
+                String::from("quick match"),
+            )
+        });
+        let rooms = waiting.rooms.iter().filter_map(|(code, room)| {
+            let host = room.host.as_ref()?;
+            Some((host.archive_name(), format!("room {}", code)))
+        });
+        queue.chain(rooms).collect()
+    }
+
+    // Remove the players who have been waiting for longer than the timeout
+    pub fn remove_idle(&self, timeout: Duration) -> Vec<Entrant> {
+        self.remove_if(|_, since| since.elapsed() > timeout)
+    }
+
+    // Remove the waiting players whose connection was closed
+    pub fn remove_disconnected(&self) -> Vec<Entrant> {
+        self.remove_if(|entrant, _| entrant.link.closed())
+    }
+
+    // Remove the waiting players with the given account
+    pub fn remove(&self, account: &str) -> Vec<Entrant> {
+        self.remove_if(|entrant, _| entrant.account.as_deref() == Some(account))
+    }
+
+    // Remove the waiting players matching a condition on them and on when they started waiting
+    fn remove_if(&self, matches: impl Fn(&Entrant, Instant) -> bool) -> Vec<Entrant> {
+        let mut waiting = self.0.lock().unwrap();
+        let mut removed = Vec::new();
+        for candidate in mem::take(&mut waiting.queue) {
+            if matches(&candidate.entrant, candidate.since) {
+                removed.push(candidate.entrant);
+            } else {
+                waiting.queue.push(candidate);
+            }
+        }
+        let codes: Vec<_> = waiting
+            .rooms
+            .iter()
+            .filter(|(_, room)| {
+                room.host
+                    .as_ref()
+                    .is_some_and(|host| matches(host, room.since))
+            })
+            .map(|(code, _)| code.clone())
+            .collect();
+        for code in codes {
+            removed.extend(waiting.rooms.remove(&code).unwrap().host);
+        }
+        removed
+    }
+
+    // Remove all the waiting players
+    pub fn clear(&self) -> Vec<Entrant> {
+        let mut waiting = self.0.lock().unwrap();
+        let queue = mem::take(&mut waiting.queue).into_iter();
+        let rooms = mem::take(&mut waiting.rooms).into_values();
+        queue
+            .map(|candidate| candidate.entrant)
+            .chain(rooms.filter_map(|room| room.host))
+            .collect()
+    }
+
+    // Codes and names of the public rooms waiting for an opponent
+    pub fn open_rooms(&self) -> Vec<(String, String)> {
+        let mut rooms: Vec<_> = self
+            .0
+            .lock()
+            .unwrap()
+            .rooms
+            .iter()
+            .filter(|(_, room)| !room.private && room.host.is_some())
+            .map(|(code, room)| (code.clone(), room.name.clone()))
+            .collect();
+        rooms.sort();
+        rooms
+    }
+}
+
+// Tell players why they are disconnected
+pub async fn turn_away(players: Vec<Entrant>, reason: &str) {
+    for player in players {
+        let (_, mut writer) = player.link.split();
+        let _ = writer.send(&Notice(reason.to_owned())).await;
+        writer.shutdown().await;
+    }
+}
+
+fn new_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_LENGTH)
+        .map(|_| CODE_CHARACTERS[rng.gen_range(0..CODE_CHARACTERS.len())] as char)
+        .collect()
+}
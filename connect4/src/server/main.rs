@@ -1,9 +1,40 @@
-use clap::{Arg, Command};
-use connect4::server;
+use clap::{Arg, ArgMatches, Command};
+use connect4::{
+    clock::TimeControl,
+    server::{
+        self, positive_seconds, seconds, Config, Format, HintPolicy, Limits, Settings,
+        TournamentSettings, MAX_SERIES_LENGTH,
+    },
+};
+use std::{
+    io::{self, IsTerminal},
+    panic,
+    path::{Path, PathBuf},
+    process, thread,
+    time::Duration,
+};
+use tracing_subscriber::EnvFilter;
 
 fn main() {
-    let socket_address = parse_args();
-    server::run(socket_address);
+    let (socket_address, settings, log_level) = parse_args();
+    init_logging(&log_level);
+    server::run(socket_address, settings);
+}
+
+// Write the logs on the standard error, so that they do not mix with the answers of the console.
+// RUST_LOG overrides the level given on the command line.
+fn init_logging(level: &str) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(io::stderr().is_terminal())
+        .with_writer(io::stderr)
+        .init();
+    // Log the panics, which only stop the task they happened in
+    panic::set_hook(Box::new(|info| {
+        let thread = thread::current();
+        tracing::error!(thread = thread.name().unwrap_or("unnamed"), "{}", info);
+    }));
 }
 
 fn cli() -> Command<'static> {
@@ -11,6 +42,26 @@ fn cli() -> Command<'static> {
         .author("Romain Ageron & Thomas Brilland")
         .version("0.1.0")
         .about("Online server for Connect4")
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .takes_value(true)
+                .help(
+                    "TOML file with the settings of the server, in the sections network, limits, \
+                    games, bots and files. The options given on the command line override it.",
+                ),
+        )
+        .arg(
+            Arg::new("accounts")
+                .short('a')
+                .long("accounts")
+                .default_value("accounts.txt")
+                .help(
+                    "File in which the accounts of the players and their ratings are saved. It \
+                    is created when the first account is registered.",
+                ),
+        )
         .arg(
             Arg::new("ipaddress")
                 .short('i')
@@ -25,22 +76,412 @@ fn cli() -> Command<'static> {
                 .default_value("50001")
                 .help("Port on which the server is listening"),
         )
+        .arg(
+            Arg::new("time")
+                .short('t')
+                .long("time")
+                .takes_value(true)
+                .conflicts_with("movetime")
+                .help(
+                    "Time in seconds given to each player for the whole game. A player who runs \
+                    out of time loses the game. By default, the time is unlimited.",
+                ),
+        )
+        .arg(
+            Arg::new("increment")
+                .long("increment")
+                .default_value("0")
+                .help("Time in seconds added to the clock of a player after each of their moves"),
+        )
+        .arg(
+            Arg::new("movetime")
+                .short('m')
+                .long("move-time")
+                .takes_value(true)
+                .help(
+                    "Time in seconds given to a player for each move. The time which is not used \
+                    is not carried over to the next moves.",
+                ),
+        )
+        .arg(
+            Arg::new("archive")
+                .long("archive")
+                .default_value("archive.txt")
+                .help(
+                    "File in which the finished games are archived, with their players, moves, \
+                    result and times. Clients can search it with connect4_client archive.",
+                ),
+        )
+        .arg(
+            Arg::new("botdepths")
+                .long("bot-depths")
+                .default_value("1,3,5,7,9")
+                .help(
+                    "Depths of the search of the bots of the server, separated by commas. The \
+                    first one is the depth of the bots of level 1, and so on.",
+                ),
+        )
+        .arg(
+            Arg::new("botworkers")
+                .long("bot-workers")
+                .default_value("2")
+                .help(
+                    "Maximum number of moves searched by the bots at the same time. The other \
+                    bots wait for their turn.",
+                ),
+        )
+        .arg(Arg::new("series").long("series").default_value("1").help(
+            "Number of games of a series between two players, with the colors swapped \
+                    after each game. The series stops as soon as a player cannot be caught up, \
+                    then the players may play a rematch.",
+        ))
+        .arg(
+            Arg::new("hints")
+                .long("hints")
+                .default_value("unrated")
+                .possible_values(["always", "unrated", "never"])
+                .help(
+                    "Games in which the players may ask their client for hints and for the \
+                    evaluation of the AI. Rated games are the ones between two players who \
+                    logged in.",
+                ),
+        )
+        .arg(
+            Arg::new("tournament")
+                .long("tournament")
+                .takes_value(true)
+                .possible_values(["round-robin", "swiss", "knockout"])
+                .help(
+                    "Host a tournament between bots of the server and players who log in and join \
+                    it with connect4_client --tournament. Each pairing plays two games, one with \
+                    each color.",
+                ),
+        )
+        .arg(
+            Arg::new("tournamentbots")
+                .long("tournament-bots")
+                .default_value("")
+                .help(
+                    "Levels of the bots taking part in the tournament, separated by commas. The \
+                    bots are seeded in this order, before the players in their order of arrival.",
+                ),
+        )
+        .arg(
+            Arg::new("tournamentplayers")
+                .long("tournament-players")
+                .default_value("0")
+                .help(
+                    "Number of players taking part in the tournament. It starts when the last \
+                    one joins it, or right away if there are only bots.",
+                ),
+        )
+        .arg(Arg::new("rounds").long("rounds").takes_value(true).help(
+            "Number of rounds of a Swiss tournament. By default, there are as many \
+                    rounds as needed to eliminate the participants of a knockout.",
+        ))
+        .arg(
+            Arg::new("tournamenttable")
+                .long("tournament-table")
+                .default_value("tournament.txt")
+                .help("File in which the standings of the tournament are written after each round"),
+        )
+        .arg(Arg::new("metrics").long("metrics").takes_value(true).help(
+            "Local port on which the metrics of the server are served over HTTP at \
+                    /metrics, in the Prometheus text format. By default, they are not served.",
+        ))
+        .arg(
+            Arg::new("metricsip")
+                .long("metrics-ip")
+                .default_value("127.0.0.1")
+                .help("IP address on which the metrics are served"),
+        )
+        .arg(
+            Arg::new("maxgames")
+                .long("max-games")
+                .takes_value(true)
+                .help(
+                    "Maximum number of games played at the same time, beyond which the players \
+                    are told that the server is full. The games of a tournament are not limited.",
+                ),
+        )
+        .arg(
+            Arg::new("maxconnections")
+                .long("max-connections")
+                .takes_value(true)
+                .help("Maximum number of clients connected at the same time"),
+        )
+        .arg(
+            Arg::new("maxconnectionsperip")
+                .long("max-connections-per-ip")
+                .takes_value(true)
+                .help("Maximum number of clients connected at the same time from an IP address"),
+        )
+        .arg(
+            Arg::new("idletimeout")
+                .long("idle-timeout")
+                .takes_value(true)
+                .help(
+                    "Time in seconds after which a player who does not move loses the game, and \
+                    a player waiting for an opponent is disconnected. By default, they can wait \
+                    as long as they want.",
+                ),
+        )
+        .arg(
+            Arg::new("loglevel")
+                .long("log-level")
+                .default_value("info")
+                .possible_values(["error", "warn", "info", "debug", "trace"])
+                .help(
+                    "Level of the logs written on the standard error. At the debug level, every \
+                    move is logged. The RUST_LOG environment variable overrides it.",
+                ),
+        )
+        .arg(
+            Arg::new("grace")
+                .short('g')
+                .long("grace")
+                .default_value("30")
+                .help(
+                    "Time in seconds given to a disconnected player to come back to the game \
+                    before losing it. While it is their turn, their clock keeps running.",
+                ),
+        )
         .after_help(
-            "The main thread handles connections. As soon as two players are in the queue, \
-            the game starts on a dedicated thread. Several games can be played simultaneously. \
-            The state of the game is maintained on the server side, not on the client side.",
+            "The server runs on an asynchronous runtime: each connection and each game is \
+            handled by its own task. As soon as two players are in the queue, the game starts. \
+            Several games can be played simultaneously. \
+            The state of the game is maintained on the server side, not on the client side. \
+            The clocks are also maintained by the server and sent to the players with each \
+            request for a move. Each player receives a token which lets them resume the game \
+            if their connection is lost. Players may log in to an account: games between two \
+            players who logged in are rated with the Elo system, and the quick matches pair \
+            players of similar ratings. Clients can also play against the bots of the server, \
+            whose levels are set by --bot-depths, and the server can host a round-robin, Swiss \
+            or knockout tournament between bots and players. \
+            The server reads administration commands on its standard input: type help to list \
+            them. It logs the connections, games and errors on its standard error, and can serve \
+            metrics for Prometheus with --metrics.",
         )
 }
 
-fn parse_args() -> (String, u16) {
-    let app = cli();
-    let matches = app.get_matches();
-    let ip = matches.value_of("ipaddress").unwrap().trim().to_owned();
-    let port: u16 = matches
-        .value_of("port")
+fn parse_time(value: &str) -> Duration {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .and_then(seconds)
+        .expect("Unvalid value for a time. It should be a number of seconds, possibly 0.")
+}
+
+// Time controls and timeouts of 0 seconds would end the games right away
+fn parse_positive_time(value: &str) -> Duration {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .and_then(positive_seconds)
+        .expect("Unvalid value for a time. It should be a positive number of seconds.")
+}
+
+fn parse_seconds(arg_matches: &ArgMatches, arg: &str) -> Option<Duration> {
+    arg_matches.value_of(arg).map(parse_positive_time)
+}
+
+fn parse_tournament(matches: &ArgMatches, levels: usize) -> Option<TournamentSettings> {
+    let format = matches.value_of("tournament")?;
+    let mut bots: Vec<u8> = Vec::new();
+    for level in matches
+        .value_of("tournamentbots")
+        .unwrap()
+        .split(',')
+        .filter(|level| !level.trim().is_empty())
+    {
+        match level.trim().parse() {
+            Ok(level) if (1..=levels).contains(&(level as usize)) && !bots.contains(&level) => {
+                bots.push(level)
+            }
+            _ => panic!(
+                "Unvalid level of bot for the tournament. The levels should be different integers \
+                between 1 and {}.",
+                levels
+            ),
+        }
+    }
+    let players: usize = matches
+        .value_of("tournamentplayers")
         .unwrap()
         .trim()
         .parse()
-        .expect("Unvalid value for port. It should be an integer between 0 and 65,535.");
-    (ip, port)
+        .expect("Unvalid number of players. It should be a positive integer.");
+    let participants = bots.len() + players;
+    if participants < 2 {
+        panic!("A tournament needs at least two participants.");
+    }
+    let format = match format {
+        "round-robin" => Format::RoundRobin,
+        "knockout" => Format::Knockout,
+        _ => {
+            // Enough rounds to separate the participants, as in a knockout
+            let rounds = match matches.value_of("rounds") {
+                Some(rounds) => match rounds.trim().parse() {
+                    Ok(rounds) if rounds > 0 => rounds,
+                    _ => panic!("Unvalid number of rounds. It should be a positive integer."),
+                },
+                None => participants.next_power_of_two().trailing_zeros() as usize,
+            };
+            Format::Swiss(rounds)
+        }
+    };
+    Some(TournamentSettings {
+        format,
+        bots,
+        players,
+        table_file: matches.value_of("tournamenttable").map(PathBuf::from),
+    })
+}
+
+fn parse_port(port: &str) -> u16 {
+    port.trim()
+        .parse()
+        .expect("Unvalid value for port. It should be an integer between 0 and 65,535.")
+}
+
+fn parse_positive(value: &str, what: &str) -> usize {
+    match value.trim().parse() {
+        Ok(value) if value > 0 => value,
+        _ => panic!("Unvalid {}. It should be a positive integer.", what),
+    }
+}
+
+// Value of an option given on the command line, or else in the configuration file, or else the
+// default value of the option
+fn setting<T>(matches: &ArgMatches, arg: &str, file: Option<T>, parse: impl Fn(&str) -> T) -> T {
+    match (matches.occurrences_of(arg), file) {
+        (0, Some(value)) => value,
+        _ => parse(matches.value_of(arg).unwrap()),
+    }
+}
+
+// Same as `setting` for the options without a default value
+fn optional_setting<T>(
+    matches: &ArgMatches,
+    arg: &str,
+    file: Option<T>,
+    parse: impl Fn(&str) -> T,
+) -> Option<T> {
+    matches.value_of(arg).map(parse).or(file)
+}
+
+// Read the configuration file, exits with all the errors found in it if it is not valid
+fn load_config(path: &str) -> Config {
+    Config::load(Path::new(path)).unwrap_or_else(|errors| {
+        eprintln!("Unvalid configuration file {}:", path);
+        for error in errors {
+            eprintln!("  {}", error.trim_end().replace('\n', "\n  "));
+        }
+        process::exit(1);
+    })
+}
+
+fn parse_args() -> ((String, u16), Settings, String) {
+    let app = cli();
+    let matches = app.get_matches();
+    let Config {
+        network,
+        limits,
+        games,
+        bots,
+        files,
+    } = matches
+        .value_of("config")
+        .map(load_config)
+        .unwrap_or_default();
+    let trim = |value: &str| value.trim().to_owned();
+    let ip = setting(&matches, "ipaddress", network.ip, trim);
+    let port = setting(&matches, "port", network.port, parse_port);
+    // The time control of the file is ignored when one is given on the command line
+    let (time, move_time, file_increment) = match (
+        parse_seconds(&matches, "time"),
+        parse_seconds(&matches, "movetime"),
+    ) {
+        (None, None) => (
+            games.time.and_then(positive_seconds),
+            games.move_time.and_then(positive_seconds),
+            games.increment.and_then(seconds),
+        ),
+        (time, move_time) => (time, move_time, None),
+    };
+    let increment = setting(&matches, "increment", file_increment, parse_time);
+    let time_control = match (time, move_time) {
+        (Some(base), _) => TimeControl::Increment(base, increment),
+        (_, Some(time)) => TimeControl::PerMove(time),
+        _ => TimeControl::Unlimited,
+    };
+    if increment > Duration::ZERO && !matches!(time_control, TimeControl::Increment(..)) {
+        panic!("An increment needs a time for the whole game.");
+    }
+    let bot_depths = setting(&matches, "botdepths", bots.depths, |depths| {
+        depths
+            .split(',')
+            .map(|depth| parse_positive(depth, "value for a depth"))
+            .collect()
+    });
+    let metrics_port = optional_setting(&matches, "metrics", network.metrics_port, parse_port);
+    let metrics_ip = setting(&matches, "metricsip", network.metrics_ip, trim);
+    let settings = Settings {
+        time_control,
+        grace_period: setting(&matches, "grace", games.grace.and_then(seconds), parse_time),
+        accounts_file: Some(setting(&matches, "accounts", files.accounts, |file| {
+            PathBuf::from(file)
+        })),
+        archive_file: Some(setting(&matches, "archive", files.archive, |file| {
+            PathBuf::from(file)
+        })),
+        tournament: parse_tournament(&matches, bot_depths.len()),
+        bot_depths,
+        series_length: setting(&matches, "series", games.series, |length| {
+            match length.trim().parse() {
+                Ok(length) if (1..=MAX_SERIES_LENGTH).contains(&length) => length,
+                _ => panic!(
+                    "Unvalid length of series. It should be an integer between 1 and {}.",
+                    MAX_SERIES_LENGTH
+                ),
+            }
+        }),
+        bot_workers: setting(&matches, "botworkers", bots.workers, |workers| {
+            parse_positive(workers, "number of workers")
+        }),
+        hints: setting(
+            &matches,
+            "hints",
+            games.hints.as_deref().and_then(HintPolicy::from_name),
+            |hints| HintPolicy::from_name(hints).unwrap(),
+        ),
+        metrics_address: metrics_port.map(|port| (metrics_ip, port)),
+        limits: Limits {
+            max_games: optional_setting(&matches, "maxgames", limits.max_games, |games| {
+                parse_positive(games, "number of games")
+            }),
+            max_connections: optional_setting(
+                &matches,
+                "maxconnections",
+                limits.max_connections,
+                |connections| parse_positive(connections, "number of connections"),
+            ),
+            max_connections_per_ip: optional_setting(
+                &matches,
+                "maxconnectionsperip",
+                limits.max_connections_per_ip,
+                |connections| parse_positive(connections, "number of connections"),
+            ),
+            idle_timeout: optional_setting(
+                &matches,
+                "idletimeout",
+                limits.idle_timeout.and_then(positive_seconds),
+                parse_positive_time,
+            ),
+        },
+    };
+    let log_level = matches.value_of("loglevel").unwrap().to_owned();
+    ((ip, port), settings, log_level)
 }
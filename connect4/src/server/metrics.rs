@@ -0,0 +1,231 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering::Relaxed},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time,
+};
+use tracing::{info, warn};
+
+// Time given to a client of the metrics endpoint to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Maximum size of a request to the metrics endpoint, only its first line is used
+const MAX_REQUEST_LENGTH: usize = 4096;
+
+// Ways a game can end, as labels of the finished games
+const ENDINGS: [&str; 7] = [
+    "normal",
+    "time_forfeit",
+    "abandoned",
+    "resignation",
+    "agreement",
+    "adjourned",
+    "aborted",
+];
+
+#[derive(Default)]
+struct Counters {
+    connections: AtomicU64,
+    games_started: AtomicU64,
+    games_finished: [AtomicU64; ENDINGS.len()],
+    game_micros: AtomicU64, // total duration of the finished games
+    game_moves: AtomicU64,  // total number of moves of the finished games
+    disconnections: AtomicU64,
+    reconnections: AtomicU64,
+    bot_moves: AtomicU64,
+    bot_micros: AtomicU64, // total time between the requests for a move and the moves of the bots
+}
+
+// Counters of the activity of the server, exported in the Prometheus text format
+#[derive(Clone, Default)]
+pub struct Metrics(Arc<Counters>);
+
+impl Metrics {
+    pub fn connection(&self) {
+        self.0.connections.fetch_add(1, Relaxed);
+    }
+
+    pub fn game_started(&self) {
+        self.0.games_started.fetch_add(1, Relaxed);
+    }
+
+    // Record a finished game, the ending is one of ENDINGS
+    pub fn game_finished(&self, ending: &str, duration: Duration, moves: usize) {
+        let index = ENDINGS.iter().position(|&name| name == ending).unwrap();
+        self.0.games_finished[index].fetch_add(1, Relaxed);
+        self.0
+            .game_micros
+            .fetch_add(duration.as_micros() as u64, Relaxed);
+        self.0.game_moves.fetch_add(moves as u64, Relaxed);
+    }
+
+    // A player lost their connection during a game
+    pub fn disconnection(&self) {
+        self.0.disconnections.fetch_add(1, Relaxed);
+    }
+
+    // A player came back to their game
+    pub fn reconnection(&self) {
+        self.0.reconnections.fetch_add(1, Relaxed);
+    }
+
+    // A bot played a move, the latency includes the time waiting for a free worker
+    pub fn bot_move(&self, latency: Duration) {
+        self.0.bot_moves.fetch_add(1, Relaxed);
+        self.0
+            .bot_micros
+            .fetch_add(latency.as_micros() as u64, Relaxed);
+    }
+
+    // Metrics in the Prometheus text format
+    pub fn export(&self) -> String {
+        let counters = &self.0;
+        let load = |counter: &AtomicU64| counter.load(Relaxed);
+        let seconds = |counter: &AtomicU64| load(counter) as f64 / 1e6;
+        let finished: Vec<u64> = counters.games_finished.iter().map(load).collect();
+        let total_finished: u64 = finished.iter().sum();
+        let mut text = String::new();
+        header(
+            &mut text,
+            "connections_total",
+            "counter",
+            "Connections accepted by the server.",
+        );
+        let _ = writeln!(
+            text,
+            "connect4_connections_total {}",
+            load(&counters.connections)
+        );
+        header(&mut text, "games_active", "gauge", "Games being played.");
+        let active = load(&counters.games_started).saturating_sub(total_finished);
+        let _ = writeln!(text, "connect4_games_active {}", active);
+        header(
+            &mut text,
+            "games_finished_total",
+            "counter",
+            "Finished games, by the way they ended.",
+        );
+        for (ending, count) in ENDINGS.iter().zip(finished) {
+            let _ = writeln!(
+                text,
+                "connect4_games_finished_total{{ending=\"{}\"}} {}",
+                ending, count
+            );
+        }
+        header(
+            &mut text,
+            "game_duration_seconds",
+            "summary",
+            "Duration of the finished games.",
+        );
+        let _ = writeln!(
+            text,
+            "connect4_game_duration_seconds_sum {}\nconnect4_game_duration_seconds_count {}",
+            seconds(&counters.game_micros),
+            total_finished
+        );
+        header(
+            &mut text,
+            "game_moves",
+            "summary",
+            "Number of moves of the finished games.",
+        );
+        let _ = writeln!(
+            text,
+            "connect4_game_moves_sum {}\nconnect4_game_moves_count {}",
+            load(&counters.game_moves),
+            total_finished
+        );
+        header(
+            &mut text,
+            "disconnections_total",
+            "counter",
+            "Connections of players lost during a game.",
+        );
+        let _ = writeln!(
+            text,
+            "connect4_disconnections_total {}",
+            load(&counters.disconnections)
+        );
+        header(
+            &mut text,
+            "reconnections_total",
+            "counter",
+            "Players who came back to their game.",
+        );
+        let _ = writeln!(
+            text,
+            "connect4_reconnections_total {}",
+            load(&counters.reconnections)
+        );
+        header(
+            &mut text,
+            "bot_move_seconds",
+            "summary",
+            "Time taken by the bots to play a move, waiting for a worker included.",
+        );
+        let _ = writeln!(
+            text,
+            "connect4_bot_move_seconds_sum {}\nconnect4_bot_move_seconds_count {}",
+            seconds(&counters.bot_micros),
+            load(&counters.bot_moves)
+        );
+        text
+    }
+}
+
+// Describe a metric before its samples
+fn header(text: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(text, "# HELP connect4_{} {}", name, help);
+    let _ = writeln!(text, "# TYPE connect4_{} {}", name, kind);
+}
+
+// Serve the metrics over HTTP at /metrics
+pub async fn serve(listener: TcpListener, metrics: Metrics) {
+    if let Ok(address) = listener.local_addr() {
+        info!(%address, "metrics served over HTTP");
+    }
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(respond(stream, metrics.clone()));
+            }
+            Err(error) => warn!(%error, "unable to accept a connection to the metrics"),
+        }
+    }
+}
+
+// Answer a single request, then close the connection
+async fn respond(mut stream: TcpStream, metrics: Metrics) {
+    let mut request = Vec::new();
+    let mut buffer = [0; 512];
+    while !request.windows(2).any(|end| end == b"\r\n") && request.len() < MAX_REQUEST_LENGTH {
+        match time::timeout(REQUEST_TIMEOUT, stream.read(&mut buffer)).await {
+            Ok(Ok(read)) if read > 0 => request.extend_from_slice(&buffer[..read]),
+            _ => return,
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.split_whitespace();
+    let (status, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.export()),
+        (Some("GET"), _) => ("404 Not Found", String::from("Not found, see /metrics\n")),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+        Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
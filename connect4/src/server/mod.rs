@@ -1,3 +1,16 @@
+mod accounts;
+mod bots;
+mod config;
+mod console;
+mod limits;
+mod lobby;
+mod metrics;
+mod registry;
 mod server;
+mod session;
+mod tournament;
 
-pub use server::run;
+pub use config::{positive_seconds, seconds, Config};
+pub use limits::Limits;
+pub use server::{play_local, run, HintPolicy, LocalPlayer, Settings, MAX_SERIES_LENGTH};
+pub use tournament::{Format, TournamentSettings};
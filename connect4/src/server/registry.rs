@@ -0,0 +1,130 @@
+use super::{limits::Slot, session::Event};
+use crate::{
+    archive,
+    game_logic::Player::{self, *},
+};
+use rand::Rng;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Arc, Mutex},
+};
+use tokio::{net::TcpStream, sync::mpsc::Sender};
+
+// Connection handed over to the task of a running game
+pub enum Arrival {
+    Player(Player, TcpStream), // a player who came back to the game
+    Spectator(TcpStream, Slot),
+}
+
+// Public id of a game, and secret resume tokens of red and yellow
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GameKeys {
+    pub id: u32,
+    pub tokens: (u64, u64),
+}
+
+// Seat of a running game: the color of the player and the channel to the task of the game
+pub type Seat = (Player, Sender<Event>);
+
+// Running game, as listed to the administrator: names of red and yellow in the archive and start
+// time in seconds since the Unix epoch
+#[derive(Clone, Debug)]
+pub struct GameInfo {
+    pub id: u32,
+    pub players: (String, String),
+    pub start: u64,
+}
+
+#[derive(Default)]
+struct Games {
+    last_id: u32,
+    seats: HashMap<u64, Seat>,
+    games: HashMap<u32, (Sender<Event>, GameInfo)>,
+    prompts: Vec<Sender<Event>>, // sessions offering a rematch between two games
+    closed: bool,                // the server is shutting down, no new game should start
+}
+
+// Ids and resume tokens of the running games. Spectators and reconnecting players are handed to
+// the task of the game through its channel.
+#[derive(Clone, Default)]
+pub struct Registry(Arc<Mutex<Games>>);
+
+impl Registry {
+    // Create the keys of a new game between the given players, whose task receives its events
+    // through `sender`
+    pub fn register(&self, sender: &Sender<Event>, players: (String, String)) -> GameKeys {
+        let mut games = self.0.lock().unwrap();
+        games.last_id += 1;
+        let id = games.last_id;
+        let info = GameInfo {
+            id,
+            players,
+            start: archive::now(),
+        };
+        games.games.insert(id, (sender.clone(), info));
+        let mut new_token = |player| loop {
+            let token = rand::thread_rng().gen();
+            if let Entry::Vacant(entry) = games.seats.entry(token) {
+                entry.insert((player, sender.clone()));
+                return token;
+            }
+        };
+        let tokens = (new_token(Red), new_token(Yellow));
+        GameKeys { id, tokens }
+    }
+
+    // Color and channel of the game of a resume token
+    pub fn seat(&self, token: u64) -> Option<Seat> {
+        self.0.lock().unwrap().seats.get(&token).cloned()
+    }
+
+    // Channel of the game with the given id
+    pub fn game(&self, id: u32) -> Option<Sender<Event>> {
+        Some(self.0.lock().unwrap().games.get(&id)?.0.clone())
+    }
+
+    // Running games with their channels, by id
+    pub fn games(&self) -> Vec<(Sender<Event>, GameInfo)> {
+        let mut games: Vec<_> = self.0.lock().unwrap().games.values().cloned().collect();
+        games.sort_by_key(|(_, info)| info.id);
+        games
+    }
+
+    // Stop new games from starting, once the running ones are over
+    pub fn close(&self) {
+        self.0.lock().unwrap().closed = true;
+    }
+
+    pub fn closed(&self) -> bool {
+        self.0.lock().unwrap().closed
+    }
+
+    // Record a session offering a rematch, so that a shutdown can end the offer. Returns false if
+    // the server is shutting down.
+    pub fn hold(&self, sender: &Sender<Event>) -> bool {
+        let mut games = self.0.lock().unwrap();
+        if games.closed {
+            return false;
+        }
+        games.prompts.push(sender.clone());
+        true
+    }
+
+    pub fn release(&self, sender: &Sender<Event>) {
+        let mut games = self.0.lock().unwrap();
+        games.prompts.retain(|prompt| !prompt.same_channel(sender));
+    }
+
+    // Channels of the sessions offering a rematch
+    pub fn prompts(&self) -> Vec<Sender<Event>> {
+        self.0.lock().unwrap().prompts.clone()
+    }
+
+    // Forget the keys of a game which is over
+    pub fn unregister(&self, keys: GameKeys) {
+        let mut games = self.0.lock().unwrap();
+        games.games.remove(&keys.id);
+        games.seats.remove(&keys.tokens.0);
+        games.seats.remove(&keys.tokens.1);
+    }
+}
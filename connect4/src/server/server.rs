@@ -1,66 +1,624 @@
+use super::{
+    accounts::Accounts,
+    bots::Bots,
+    console,
+    limits::{Capacity, Limits, Slot},
+    lobby::{self, Entrant, Lobby},
+    metrics::{self, Metrics},
+    registry::{Arrival, Registry},
+    session::{Event, Interruption, Session},
+    tournament::{self, Registrations, TournamentSettings},
+};
 use crate::{
-    communication::Message::{self, *},
-    game_logic::{Connect4, Player::*},
+    archive::{self, Archive, GameRecord},
+    clock::{Clock, TimeControl},
+    communication::{
+        transport::{ChannelEnd, Link},
+        Message::{self, *},
+        Termination,
+    },
+    game_logic::Player::{self, *},
 };
+use rand::Rng;
 use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::AsyncWrite,
     net::{TcpListener, TcpStream},
-    thread,
+    runtime::Runtime,
+    sync::mpsc::{self, error::SendError, Sender},
+    time,
 };
+use tracing::{info, info_span, warn, Instrument};
+
+// Maximum number of pending events of a game, beyond which the tasks reading the connections wait
+const EVENTS_CAPACITY: usize = 64;
+
+// Time given to a client to send its first message
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Maximum number of rooms sent to a client listing them, so that the list fits in a message
+const MAX_LISTED_ROOMS: usize = 20;
+
+// Time between two attempts to pair the players waiting for a quick match
+const MATCHMAKING_INTERVAL: Duration = Duration::from_secs(1);
+
+// Maximum number of games of a series, whose scores are sent in half points on a byte
+pub const MAX_SERIES_LENGTH: usize = 100;
+
+// Time given to the players to accept a rematch
+const REMATCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Settings shared by all the games of the server
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Settings {
+    pub time_control: TimeControl,
+    pub grace_period: Duration, // time given to a disconnected player to come back
+    pub accounts_file: Option<PathBuf>, // accounts are not saved without a file
+    pub archive_file: Option<PathBuf>, // finished games are not saved without a file
+    pub bot_depths: Vec<usize>, // depth of the search of each level of bot
+    pub bot_workers: usize,     // maximum number of searches of the bots at the same time
+    pub series_length: usize,   // maximum number of games of a series, which the best player wins
+    pub tournament: Option<TournamentSettings>,
+    pub metrics_address: Option<(String, u16)>, // the metrics are only served with an address
+    pub limits: Limits,
+    pub hints: HintPolicy,
+}
+
+// Games in which the players may ask their client for the help of the AI. Rated games are the ones
+// between two players who logged in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HintPolicy {
+    Always,
+    Unrated,
+    Never,
+}
+
+impl HintPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(HintPolicy::Always),
+            "unrated" => Some(HintPolicy::Unrated),
+            "never" => Some(HintPolicy::Never),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            time_control: TimeControl::Unlimited,
+            grace_period: Duration::from_secs(30),
+            accounts_file: None,
+            archive_file: None,
+            bot_depths: vec![1, 3, 5, 7, 9],
+            bot_workers: 2,
+            series_length: 1,
+            tournament: None,
+            metrics_address: None,
+            limits: Limits::default(),
+            hints: HintPolicy::Unrated,
+        }
+    }
+}
+
+// Everything the tasks of the server share
+#[derive(Clone)]
+pub(super) struct Shared {
+    pub(super) lobby: Lobby,
+    pub(super) registry: Registry,
+    accounts: Accounts,
+    archive: Archive,
+    pub(super) bots: Bots,
+    pub(super) registrations: Registrations,
+    pub(super) metrics: Metrics,
+    capacity: Capacity,
+    pub(super) settings: Arc<RwLock<Settings>>, // the administrator may change them for the next games
+}
+
+impl Shared {
+    fn new(settings: Settings) -> Self {
+        let metrics = Metrics::default();
+        // The limits of the capacity are the ones of the settings changed by the administrator
+        let shared_settings = Arc::new(RwLock::new(settings.clone()));
+        Self {
+            lobby: Lobby::default(),
+            registry: Registry::default(),
+            accounts: Accounts::load(settings.accounts_file.clone()),
+            archive: Archive::load(settings.archive_file.clone()),
+            bots: Bots::new(
+                settings.bot_depths.clone(),
+                settings.bot_workers,
+                metrics.clone(),
+            ),
+            registrations: Registrations::new(settings.tournament.as_ref()),
+            metrics,
+            capacity: Capacity::new(Arc::clone(&shared_settings)),
+            settings: shared_settings,
+        }
+    }
+
+    // Settings for a new game
+    pub(super) fn settings(&self) -> Settings {
+        self.settings.read().unwrap().clone()
+    }
+}
+
+// Player of a game played in the same process as the server
+pub enum LocalPlayer {
+    Client(ChannelEnd), // the other end is used by a client, through a Channel
+    Bot(usize),         // depth of the search
+}
 
 // Run the server
-pub fn run(socket_address: (String, u16)) {
-    let listener = TcpListener::bind(socket_address).unwrap();
-    let mut queue = None;
-    for stream in listener.incoming() {
-        if let Ok(player) = stream {
-            match queue {
-                Some(other_player) => {
-                    queue = None;
-                    thread::spawn(|| {
-                        play_game(other_player, player);
-                    });
+pub fn run(socket_address: (String, u16), settings: Settings) {
+    Runtime::new()
+        .unwrap()
+        .block_on(serve(socket_address, settings));
+}
+
+// Accept the connections, each client is handled by its own task, until the administrator shuts
+// the server down from the console
+async fn serve(socket_address: (String, u16), settings: Settings) {
+    let listener = TcpListener::bind(socket_address).await.unwrap();
+    info!(address = %listener.local_addr().unwrap(), "server listening");
+    let shared = Shared::new(settings);
+    if let Some(address) = shared.settings().metrics_address {
+        let metrics_listener = TcpListener::bind(address).await.unwrap();
+        tokio::spawn(metrics::serve(metrics_listener, shared.metrics.clone()));
+    }
+    tokio::spawn(matchmaking(shared.clone()));
+    // A tournament between bots only starts right away
+    if let Some(tournament) = &shared.settings().tournament {
+        if tournament.players == 0 {
+            tokio::spawn(tournament::run(
+                shared.clone(),
+                tournament.clone(),
+                Vec::new(),
+            ));
+        }
+    }
+    let console = console::run(shared.clone());
+    tokio::pin!(console);
+    let adjourn_after = loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((player, peer)) => {
+                    shared.metrics.connection();
+                    let span = info_span!("connection", %peer);
+                    match shared.capacity.connect(peer.ip()) {
+                        Some(slot) => {
+                            tokio::spawn(handshake(player, slot, shared.clone()).instrument(span));
+                        }
+                        None => {
+                            tokio::spawn(refuse(player).instrument(span));
+                        }
+                    }
+                }
+                Err(error) => warn!(%error, "unable to accept a connection"),
+            },
+            adjourn_after = &mut console => break adjourn_after,
+        }
+    };
+    drop(listener);
+    info!("shutting down");
+    console::shut_down(&shared, adjourn_after).await;
+}
+
+// Play a single game between local players, without networking. The clients have to run in other
+// threads, since this function blocks until the end of the game.
+pub fn play_local(players: (LocalPlayer, LocalPlayer), settings: Settings) {
+    Runtime::new().unwrap().block_on(async {
+        let shared = Shared::new(settings);
+        let entrant = |player| match player {
+            LocalPlayer::Client(end) => Entrant::local(Link::Channel(end)),
+            LocalPlayer::Bot(depth) => Entrant::local(shared.bots.spawn_with_depth(depth)),
+        };
+        let players = (entrant(players.0), entrant(players.1));
+        play_series(players, shared.clone()).await;
+    });
+}
+
+// Regularly pair the players waiting for a quick match who did not find an opponent of their
+// strength when they arrived, forget the ones who left and disconnect the ones who waited for
+// too long
+async fn matchmaking(shared: Shared) {
+    let mut interval = time::interval(MATCHMAKING_INTERVAL);
+    loop {
+        interval.tick().await;
+        let disconnected = shared.lobby.remove_disconnected();
+        if !disconnected.is_empty() {
+            info!(
+                players = disconnected.len(),
+                "disconnected players removed from the lobby"
+            );
+        }
+        for players in shared.lobby.match_waiting() {
+            tokio::spawn(play_series(players, shared.clone()));
+        }
+        if let Some(timeout) = shared.settings().limits.idle_timeout {
+            let idle = shared.lobby.remove_idle(timeout);
+            if !idle.is_empty() {
+                info!(players = idle.len(), "idle players removed from the lobby");
+                tokio::spawn(lobby::turn_away(
+                    idle,
+                    "Nobody came to play with you in time.",
+                ));
+            }
+        }
+    }
+}
+
+// Turn away a client beyond the limits of connections, after reading its request so that it
+// receives the notice
+async fn refuse(mut player: TcpStream) {
+    warn!("connection refused, there are too many connections");
+    read_handshake(&mut player).await;
+    let notice = Notice(String::from("The server is full, try again later."));
+    let _ = notice.write_async(&mut player).await;
+}
+
+// Read the first message of a client: either look for an opponent, resume a game or watch one.
+// Players looking for an opponent may log in first.
+async fn handshake(mut player: TcpStream, mut slot: Slot, shared: Shared) {
+    let mut message = read_handshake(&mut player).await;
+    let mut account = None;
+    if let Some(Login(name, password, register)) = message {
+        let rating = if register {
+            shared.accounts.register(name.clone(), password).await
+        } else {
+            shared.accounts.login(&name, password).await
+        };
+        // An account is only used by one connection at a time, so that it never plays itself
+        let in_use = rating.and_then(|_| shared.accounts.claim(&name));
+        match (rating, in_use) {
+            (Some(rating), Some(in_use)) => {
+                slot.log_in(in_use);
+                if Rating(rating.round() as u16)
+                    .write_async(&mut player)
+                    .await
+                    .is_err()
+                {
+                    return;
                 }
-                None => queue = Some(player),
+                info!(account = %name, register, "logged in");
+                account = Some((name, rating));
+            }
+            (Some(_), None) => {
+                info!(account = %name, "login refused, the account is already in use");
+                let notice = format!("The account {} is already playing or waiting.", name);
+                let _ = Notice(notice).write_async(&mut player).await;
+                return;
+            }
+            (None, _) => {
+                info!(account = %name, register, "login refused");
+                let _ = LoginFailed.write_async(&mut player).await;
+                return;
             }
         }
+        message = read_handshake(&mut player).await;
+    }
+    info!(request = request_name(&message), "handshake");
+    let lobby = &shared.lobby;
+    let registry = &shared.registry;
+    match message {
+        Some(Join) => {
+            if let Some(players) = lobby.quick_match(Entrant::new(player, slot, account)) {
+                play_series(players, shared.clone()).await;
+            }
+        }
+        Some(CreateRoom(name, private)) => {
+            // The game starts when another player joins the room with its code
+            let code = lobby.create_room(name, private);
+            match RoomCode(code.clone()).write_async(&mut player).await {
+                Ok(()) => lobby.open_room(&code, Entrant::new(player, slot, account)),
+                Err(_) => lobby.close_room(&code),
+            }
+        }
+        Some(JoinRoom(code)) => match lobby.join_room(&code, Entrant::new(player, slot, account)) {
+            Ok(players) => {
+                play_series(players, shared.clone()).await;
+            }
+            Err(player) => {
+                let (_, mut writer) = player.link.split();
+                let _ = writer.send(&NotFound).await;
+            }
+        },
+        Some(PlayBot(level)) => match shared.bots.spawn(level) {
+            Some(bot) => {
+                let player = Entrant::new(player, slot, account);
+                let bot = Entrant::bot(bot, level);
+                let players = if rand::thread_rng().gen() {
+                    (player, bot)
+                } else {
+                    (bot, player)
+                };
+                play_series(players, shared.clone()).await;
+            }
+            None => not_found(&mut player).await,
+        },
+        Some(JoinTournament) => match (account, &shared.settings().tournament) {
+            (Some((name, _)), Some(tournament))
+                if shared.registrations.reserve(&name, tournament.players) =>
+            {
+                let registrations = &shared.registrations;
+                if Registered.write_async(&mut player).await.is_err() {
+                    registrations.cancel(&name);
+                    return;
+                }
+                // If the registrations closed meanwhile, the player is disconnected as the other
+                // registered players
+                if let Ok(Some(players)) = registrations.register(name, player, tournament.players)
+                {
+                    tournament::run(shared.clone(), tournament.clone(), players).await
+                }
+            }
+            _ => not_found(&mut player).await,
+        },
+        Some(ListRooms) => {
+            let mut rooms = lobby.open_rooms();
+            rooms.truncate(MAX_LISTED_ROOMS);
+            let _ = Rooms(rooms).write_async(&mut player).await;
+        }
+        Some(Search(query)) => {
+            for game in shared.archive.search(&query) {
+                if ArchivedGame(game).write_async(&mut player).await.is_err() {
+                    return;
+                }
+            }
+            let _ = EndOfSearch.write_async(&mut player).await;
+        }
+        Some(Resume(token)) => match registry.seat(token) {
+            Some((color, events)) => hand_over(events, Arrival::Player(color, player)).await,
+            None => not_found(&mut player).await,
+        },
+        Some(Spectate(id)) => match registry.game(id) {
+            Some(events) => hand_over(events, Arrival::Spectator(player, slot)).await,
+            None => not_found(&mut player).await,
+        },
+        _ => (),
+    }
+}
+
+// Kind of request of a handshake, for the logs
+fn request_name(message: &Option<Message>) -> &'static str {
+    match message {
+        Some(Join) => "quick match",
+        Some(CreateRoom(..)) => "create room",
+        Some(JoinRoom(_)) => "join room",
+        Some(PlayBot(_)) => "play bot",
+        Some(JoinTournament) => "join tournament",
+        Some(ListRooms) => "list rooms",
+        Some(Search(_)) => "search archive",
+        Some(Resume(_)) => "resume",
+        Some(Spectate(_)) => "spectate",
+        Some(_) => "unexpected message",
+        None => "none before the timeout",
+    }
+}
+
+async fn read_handshake(stream: &mut TcpStream) -> Option<Message> {
+    match time::timeout(HANDSHAKE_TIMEOUT, Message::read_async(stream)).await {
+        Ok(Ok(message)) => Some(message),
+        _ => None,
     }
 }
 
-// Send the game over messages to the clients
-fn game_over<'a>(game: &Connect4, player_1: &'a mut TcpStream, player_2: &'a mut TcpStream) {
-    if let Some(winner) = game.check_winner() {
-        let (winner, loser) = winner.select(player_1, player_2);
-        Win.send_to(winner);
-        Lose.send_to(loser);
-    } else {
-        Draw.send_to(player_1);
-        Draw.send_to(player_2);
+// Give a connection to the task of a game, which may have ended in the meantime
+async fn hand_over(events: Sender<Event>, arrival: Arrival) {
+    if let Err(SendError(Event::Arrival(
+        Arrival::Player(_, mut stream) | Arrival::Spectator(mut stream, _),
+    ))) = events.send(Event::Arrival(arrival)).await
+    {
+        not_found(&mut stream).await;
     }
 }
 
-// Play the game in the server
-fn play_game(mut player_1: TcpStream, mut player_2: TcpStream) {
-    Hello(Red).send_to(&mut player_1);
-    Hello(Yellow).send_to(&mut player_2);
-    let mut game = Connect4::new();
-    while !game.over() {
-        play_turn(&mut game, &mut player_1, &mut player_2)
+async fn not_found<W: AsyncWrite + Unpin>(writer: &mut W) {
+    let _ = NotFound.write_async(writer).await;
+}
+
+// Accounts of the players and their names in the archive, in the order of their seats
+struct Identities {
+    accounts: [Option<String>; 2],
+    names: [String; 2],
+}
+
+// Session of two players, the first one is red in the first game
+fn open_session(
+    (player_1, player_2): (Entrant, Entrant),
+    shared: &Shared,
+) -> (Session, Identities) {
+    let identities = Identities {
+        accounts: [player_1.account.clone(), player_2.account.clone()],
+        names: [player_1.archive_name(), player_2.archive_name()],
+    };
+    let settings = shared.settings();
+    let session = Session::new(
+        (player_1.link, player_2.link),
+        Clock::new(settings.time_control),
+        mpsc::channel(EVENTS_CAPACITY),
+        settings.grace_period,
+        settings.limits.idle_timeout,
+        shared.metrics.clone(),
+    );
+    (session, identities)
+}
+
+// Play a single game in the server, the first player is red. Returns the winner.
+pub(super) async fn play_game(players: (Entrant, Entrant), shared: Shared) -> Option<Player> {
+    let (mut session, identities) = open_session(players, &shared);
+    let (winner, _) = play_in(&mut session, &identities, &shared).await;
+    session.close().await;
+    winner
+}
+
+// Play series of games between two players, the first one is red in the first game and the colors
+// are swapped after each game. After each series, the players are offered a rematch. The players
+// are turned away if the server already runs as many games as it can.
+async fn play_series(mut players: (Entrant, Entrant), shared: Shared) {
+    let _game = match shared.capacity.start_game() {
+        Some(permit) => permit,
+        None => {
+            warn!("game refused, there are too many games");
+            let players = vec![players.0, players.1];
+            return lobby::turn_away(players, "The server is full, try again later.").await;
+        }
+    };
+    // The connections of the players are counted until the end of the series
+    let _slots = (players.0.slot.take(), players.1.slot.take());
+    let (mut session, identities) = open_session(players, &shared);
+    let series_length = shared.settings().series_length;
+    let mut first = true;
+    loop {
+        // Points of each seat in half points. A series ends when a player cannot be caught up.
+        let mut points = [0u8; 2];
+        let mut games = 0;
+        while games < series_length
+            && points
+                .iter()
+                .all(|&points| points as usize <= series_length)
+        {
+            if !first {
+                if shared.registry.closed() {
+                    let notice = "The server is shutting down, the series ends.";
+                    for player in [Red, Yellow] {
+                        session.send(player, Notice(String::from(notice))).await;
+                    }
+                    session.close().await;
+                    return;
+                }
+                session.new_game(Clock::new(shared.settings().time_control));
+            }
+            first = false;
+            let (winner, termination) = play_in(&mut session, &identities, &shared).await;
+            if termination == Termination::Adjourned {
+                session.close().await;
+                return;
+            }
+            match winner {
+                Some(winner) => points[session.seat_of(winner)] += 2,
+                None => points = points.map(|points| points + 1),
+            }
+            games += 1;
+            for player in [Red, Yellow] {
+                let (seat, other_seat) = (session.seat_of(player), session.seat_of(player.other()));
+                session
+                    .send(player, Score(points[seat], points[other_seat]))
+                    .await;
+            }
+            if termination == Termination::Abandoned {
+                session.close().await;
+                return;
+            }
+        }
+        // The offer is skipped when the server is shutting down, and ended if it starts to
+        let held = shared.registry.hold(session.sender());
+        let rematch = held && session.ask_rematch(REMATCH_TIMEOUT).await;
+        shared.registry.release(session.sender());
+        if !rematch {
+            break;
+        }
     }
-    game_over(&game, &mut player_1, &mut player_2)
+    session.close().await;
 }
 
-// Play a turn : ask for input to the player, send the received input to both players
-fn play_turn(game: &mut Connect4, player_1: &mut TcpStream, player_2: &mut TcpStream) {
-    let (player, other) = game.to_play.select(player_1, player_2);
-    Play.send_to(player);
-    let action = match Message::receive_from(player) {
-        Action(action) if game.valid_action(action as usize) => action,
-        _ => {
-            InvalidAction.send_to(player);
-            return;
+// Play the current game of a session. Returns the winner and how the game ended. Games stopped by
+// the administrator have no winner and are only archived if they were adjourned.
+async fn play_in(
+    session: &mut Session,
+    identities: &Identities,
+    shared: &Shared,
+) -> (Option<Player>, Termination) {
+    let Shared {
+        registry,
+        accounts,
+        archive,
+        ..
+    } = shared;
+    let start = archive::now();
+    let (red, yellow) = (session.seat_of(Red), session.seat_of(Yellow));
+    let players = (
+        identities.names[red].clone(),
+        identities.names[yellow].clone(),
+    );
+    let players_accounts = [&identities.accounts[red], &identities.accounts[yellow]];
+    let hints = match shared.settings.read().unwrap().hints {
+        HintPolicy::Always => true,
+        HintPolicy::Unrated => players_accounts.iter().any(|account| account.is_none()),
+        HintPolicy::Never => false,
+    };
+    let keys = registry.register(session.sender(), players.clone());
+    let span = info_span!("game", id = keys.id);
+    info!(parent: &span, red = ?players.0, yellow = ?players.1, "game started");
+    shared.metrics.game_started();
+    let started = Instant::now();
+    for (player, token) in [(Red, keys.tokens.0), (Yellow, keys.tokens.1)] {
+        session
+            .send(player, Hello(player, session.time_control()))
+            .await;
+        session.send(player, Token(token)).await;
+        session.send(player, GameId(keys.id)).await;
+        session
+            .send(player, Players(players.0.clone(), players.1.clone()))
+            .await;
+        session.send(player, Hints(hints)).await;
+    }
+    let result = async {
+        let mut result = Ok(());
+        while result.is_ok() && !session.game.over() {
+            result = session.play_turn().await;
         }
+        result
+    }
+    .instrument(span.clone())
+    .await;
+    let (winner, termination) = match result {
+        Ok(()) => (session.game.check_winner(), Termination::Normal),
+        Err(Interruption::Forfeit(loser, termination)) => (Some(loser.other()), termination),
+        Err(Interruption::Agreed) => (None, Termination::Agreement),
+        Err(Interruption::Aborted | Interruption::Adjourned) => (None, Termination::Adjourned),
+    };
+    // Games between two players who logged in to different accounts are rated, their new ratings
+    // are sent before the result
+    let rated = termination != Termination::Adjourned && players_accounts[0] != players_accounts[1];
+    if let (true, [Some(red), Some(yellow)]) = (rated, players_accounts) {
+        let ratings = accounts.record((red, yellow), winner);
+        session.send(Red, Rating(ratings.0.round() as u16)).await;
+        session.send(Yellow, Rating(ratings.1.round() as u16)).await;
+    }
+    match result {
+        Ok(()) => session.game_over().await,
+        Err(interruption) => session.interrupt(interruption).await,
+    }
+    if result != Err(Interruption::Aborted) {
+        archive.record(GameRecord {
+            number: 0,
+            players,
+            start,
+            end: archive::now(),
+            winner,
+            termination,
+            moves: session.history.clone(),
+        });
+    }
+    let ending = match (result, termination) {
+        (Err(Interruption::Aborted), _) => "aborted",
+        (_, Termination::Normal) => "normal",
+        (_, Termination::TimeForfeit) => "time_forfeit",
+        (_, Termination::Abandoned) => "abandoned",
+        (_, Termination::Adjourned) => "adjourned",
+        (_, Termination::Resignation) => "resignation",
+        (_, Termination::Agreement) => "agreement",
     };
-    ValidAction(action).send_to(player);
-    ValidAction(action).send_to(other);
-    game.play(action as usize);
+    let duration = started.elapsed();
+    let moves = session.history.len();
+    info!(parent: &span, ?winner, ending, moves, ?duration, "game over");
+    shared.metrics.game_finished(ending, duration, moves);
+    registry.unregister(keys);
+    (winner, termination)
 }
@@ -0,0 +1,476 @@
+use super::{limits::Slot, metrics::Metrics, registry::Arrival};
+use crate::{
+    clock::{Clock, TimeControl},
+    communication::{
+        transport::{Link, LinkReader, LinkWriter},
+        Message::{self, *},
+        Termination,
+    },
+    game_logic::{
+        Connect4,
+        Player::{self, *},
+    },
+};
+use std::{
+    future::Future,
+    io::{self, ErrorKind},
+    mem,
+    time::Duration,
+};
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::{Receiver, Sender},
+    task::JoinHandle,
+    time::{self, Instant},
+};
+use tracing::{debug, info};
+
+// Maximum time to send a message, so that a client which does not read its messages cannot block
+// the game
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Everything the task of a game has to react to. The messages and disconnections of a player
+// come with the number of the connection they happened on, so that the events of a connection
+// which has been replaced are ignored. Players are identified by the color of their seat in the
+// first game of the session.
+pub enum Event {
+    Received(Player, usize, Message),
+    Disconnected(Player, usize),
+    Arrival(Arrival),
+    Control(Control),
+}
+
+// Command of the administrator of the server about a running game
+#[derive(Clone, Debug)]
+pub enum Control {
+    Kick(Player), // the player with this color loses the game, as if they had abandoned it
+    Abort,        // the game stops, without a result
+    Adjourn,      // the game stops without a result, but it is archived
+    Notice(String),
+}
+
+// Reason why a game ended before being over on the board
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Interruption {
+    Forfeit(Player, Termination), // the player lost without being beaten on the board
+    Agreed,                       // both players agreed to a draw
+    Aborted,
+    Adjourned,
+}
+
+// Connection of a player and when it was lost, if it was. The messages of the player are read by
+// a dedicated task.
+struct Seat {
+    writer: LinkWriter,
+    reader: JoinHandle<()>,
+    number: usize,
+    lost_since: Option<Instant>,
+}
+
+impl Seat {
+    fn new(player: Player, link: Link, number: usize, events: &Sender<Event>) -> Self {
+        let (reader, writer) = link.split();
+        let reader = tokio::spawn(Self::listen(player, reader, number, events.clone()));
+        Self {
+            writer,
+            reader,
+            number,
+            lost_since: None,
+        }
+    }
+
+    // Forward the messages of a connection to the task of the game
+    async fn listen(player: Player, mut reader: LinkReader, number: usize, events: Sender<Event>) {
+        loop {
+            let event = match reader.receive().await {
+                Ok(message) => Event::Received(player, number, message),
+                Err(error) if error.kind() == ErrorKind::InvalidData => continue,
+                Err(_) => Event::Disconnected(player, number),
+            };
+            let disconnected = matches!(event, Event::Disconnected(..));
+            if events.send(event).await.is_err() || disconnected {
+                return;
+            }
+        }
+    }
+
+    // Stop reading the connection and close it
+    async fn close(&mut self) {
+        self.reader.abort();
+        let _ = self.writer.shutdown().await;
+    }
+}
+
+// Send a message without waiting more than WRITE_TIMEOUT, returns false if it failed
+async fn write(sending: impl Future<Output = io::Result<()>>) -> bool {
+    matches!(time::timeout(WRITE_TIMEOUT, sending).await, Ok(Ok(())))
+}
+
+// State of a game hosted by the server. It does not depend on the connections so that a player
+// who lost their connection can resume the game. The same players may play several games in a
+// session, swapping colors each time.
+pub struct Session {
+    pub game: Connect4,
+    pub history: Vec<u8>,
+    clock: Clock,
+    seats: [Seat; 2],           // red and yellow of the first game
+    swapped: bool,              // whether the colors are swapped in the current game
+    draw_offer: Option<Player>, // player whose offer of a draw stands until their opponent moves
+    spectators: Vec<(TcpStream, Slot)>,
+    events: Receiver<Event>,
+    sender: Sender<Event>,
+    grace_period: Duration,
+    idle_timeout: Option<Duration>, // time after which a player who does not move loses the game
+    metrics: Metrics,
+}
+
+impl Session {
+    pub fn new(
+        players: (Link, Link),
+        clock: Clock,
+        (sender, events): (Sender<Event>, Receiver<Event>),
+        grace_period: Duration,
+        idle_timeout: Option<Duration>,
+        metrics: Metrics,
+    ) -> Self {
+        Self {
+            game: Connect4::new(),
+            history: Vec::new(),
+            clock,
+            seats: [
+                Seat::new(Red, players.0, 0, &sender),
+                Seat::new(Yellow, players.1, 0, &sender),
+            ],
+            swapped: false,
+            draw_offer: None,
+            spectators: Vec::new(),
+            events,
+            sender,
+            grace_period,
+            idle_timeout,
+            metrics,
+        }
+    }
+
+    // Color of the seat of a player in the first game from their color in the current game, and
+    // the other way round
+    fn tag(&self, player: Player) -> Player {
+        if self.swapped {
+            player.other()
+        } else {
+            player
+        }
+    }
+
+    // Index of the seat of the player with the given color in the current game
+    pub fn seat_of(&self, player: Player) -> usize {
+        self.tag(player).select(0, 1).0
+    }
+
+    fn seat(&mut self, player: Player) -> &mut Seat {
+        &mut self.seats[self.seat_of(player)]
+    }
+
+    // Number of the current connection of a player
+    fn connection(&self, player: Player) -> usize {
+        self.seats[self.seat_of(player)].number
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.clock.time_control()
+    }
+
+    // Channel to the task of the session, for the registry
+    pub fn sender(&self) -> &Sender<Event> {
+        &self.sender
+    }
+
+    // Start a new game between the same players, with the colors swapped
+    pub fn new_game(&mut self, clock: Clock) {
+        self.game = Connect4::new();
+        self.history.clear();
+        self.clock = clock;
+        self.swapped = !self.swapped;
+        self.draw_offer = None;
+        self.spectators.clear();
+    }
+
+    // Send a message to a player. A lost connection is noticed by the task reading it.
+    pub async fn send(&mut self, player: Player, message: Message) {
+        write(self.seat(player).writer.send(&message)).await;
+    }
+
+    // Send a message to all the spectators, forgetting those who left
+    async fn broadcast(&mut self, message: Message) {
+        let mut spectators = Vec::with_capacity(self.spectators.len());
+        for (mut spectator, slot) in mem::take(&mut self.spectators) {
+            if write(message.write_async(&mut spectator)).await {
+                spectators.push((spectator, slot));
+            }
+        }
+        self.spectators = spectators;
+    }
+
+    // Close the connections of the players
+    pub async fn close(&mut self) {
+        for seat in self.seats.iter_mut() {
+            seat.close().await;
+        }
+    }
+
+    // Replace the connection of a player who came back or add a spectator, and send them the game
+    // so far. Returns the player who came back, if any.
+    async fn accept(&mut self, arrival: Arrival) -> Option<Player> {
+        match arrival {
+            Arrival::Player(player, stream) => {
+                let number = self.connection(player) + 1;
+                let seat = Seat::new(self.tag(player), stream.into(), number, &self.sender);
+                mem::replace(self.seat(player), seat).close().await;
+                info!(?player, "player came back");
+                self.metrics.reconnection();
+                self.send(player, Hello(player, self.clock.time_control()))
+                    .await;
+                self.send(player, History(self.history.clone())).await;
+                Some(player)
+            }
+            Arrival::Spectator(mut spectator, slot) => {
+                if write(History(self.history.clone()).write_async(&mut spectator)).await {
+                    self.spectators.push((spectator, slot));
+                }
+                None
+            }
+        }
+    }
+
+    // Relay a chat message to the other player and to the spectators
+    async fn relay_chat(&mut self, player: Player, text: String) {
+        self.send(player.other(), ChatFrom(player, text.clone()))
+            .await;
+        self.broadcast(ChatFrom(player, text)).await;
+    }
+
+    // Next moment when a player loses on time, because they did not come back or because they
+    // did not move for too long, and the reason
+    fn deadline(&self, start: Instant) -> Option<(Instant, Player, Termination)> {
+        let to_play = self.game.to_play;
+        let time_forfeit = self
+            .clock
+            .remaining(to_play)
+            .map(|remaining| (start + remaining, to_play, Termination::TimeForfeit));
+        let idle = self
+            .idle_timeout
+            .map(|timeout| (start + timeout, to_play, Termination::Abandoned));
+        let abandons = [Red, Yellow].into_iter().filter_map(|player| {
+            self.seats[self.seat_of(player)]
+                .lost_since
+                .map(|lost_since| {
+                    (
+                        lost_since + self.grace_period,
+                        player,
+                        Termination::Abandoned,
+                    )
+                })
+        });
+        time_forfeit
+            .into_iter()
+            .chain(idle)
+            .chain(abandons)
+            .min_by_key(|&(deadline, ..)| deadline)
+    }
+
+    // Play a turn : ask for input to the player, send the received input to both players.
+    // Returns why the game ended during the turn, if it did.
+    pub async fn play_turn(&mut self) -> Result<(), Interruption> {
+        let to_play = self.game.to_play;
+        let start = Instant::now();
+        self.send(to_play, Play(self.clock.remaining_pair(to_play)))
+            .await;
+        loop {
+            let event = match self.deadline(start) {
+                Some((deadline, loser, termination)) => {
+                    match time::timeout_at(deadline, self.events.recv()).await {
+                        Ok(Some(event)) => event,
+                        Ok(None) | Err(_) => {
+                            self.clock.spend(to_play, start.elapsed());
+                            return Err(Interruption::Forfeit(loser, termination));
+                        }
+                    }
+                }
+                None => self.events.recv().await.unwrap(),
+            };
+            match event {
+                Event::Received(tag, number, message)
+                    if number == self.connection(self.tag(tag)) =>
+                {
+                    let player = self.tag(tag);
+                    match message {
+                        Action(action) if player == to_play => {
+                            if self.game.valid_action(action as usize) {
+                                self.play_action(action, start.elapsed()).await?;
+                                return Ok(());
+                            }
+                            self.send(player, InvalidAction).await;
+                        }
+                        Chat(text) => self.relay_chat(player, text).await,
+                        Resign => {
+                            self.clock.spend(to_play, start.elapsed());
+                            return Err(Interruption::Forfeit(player, Termination::Resignation));
+                        }
+                        OfferDraw if self.draw_offer == Some(player.other()) => {
+                            self.clock.spend(to_play, start.elapsed());
+                            return Err(Interruption::Agreed);
+                        }
+                        OfferDraw => {
+                            self.draw_offer = Some(player);
+                            self.send(player.other(), OfferDraw).await;
+                        }
+                        _ => (),
+                    }
+                }
+                Event::Disconnected(tag, number) if number == self.connection(self.tag(tag)) => {
+                    let player = self.tag(tag);
+                    info!(?player, "connection lost");
+                    self.metrics.disconnection();
+                    self.seat(player).lost_since = Some(Instant::now());
+                }
+                Event::Control(control) => self.control(control).await?,
+                Event::Arrival(arrival) => {
+                    let resumed = self.accept(arrival).await;
+                    if resumed == Some(to_play) {
+                        let mut clock = self.clock.clone();
+                        clock.spend(to_play, start.elapsed());
+                        self.send(to_play, Play(clock.remaining_pair(to_play)))
+                            .await;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // Update the clock and the game with a valid action, and send it to everyone
+    async fn play_action(&mut self, action: u8, elapsed: Duration) -> Result<(), Interruption> {
+        let to_play = self.game.to_play;
+        if !self.clock.spend(to_play, elapsed) {
+            return Err(Interruption::Forfeit(to_play, Termination::TimeForfeit));
+        }
+        self.clock.complete_move(to_play);
+        // Moving declines the draw offered by the opponent
+        if self.draw_offer == Some(to_play.other()) {
+            self.draw_offer = None;
+        }
+        debug!(player = ?to_play, column = action, ?elapsed, "move");
+        self.send(Red, ValidAction(action)).await;
+        self.send(Yellow, ValidAction(action)).await;
+        self.broadcast(ValidAction(action)).await;
+        self.game.play(action as usize);
+        self.history.push(action);
+        Ok(())
+    }
+
+    // Send the game over messages to the clients
+    pub async fn game_over(&mut self) {
+        let lines = self.game.winning_lines();
+        if let Some(winner) = self.game.check_winner() {
+            self.send(winner, Win(Termination::Normal, lines.clone()))
+                .await;
+            self.send(winner.other(), Lose(Termination::Normal, lines.clone()))
+                .await;
+        } else {
+            self.send(Red, Draw).await;
+            self.send(Yellow, Draw).await;
+        }
+        let winner = self.game.check_winner();
+        self.broadcast(GameOver(winner, Termination::Normal, lines))
+            .await;
+    }
+
+    // Send the game over messages when the game ended before being over on the board
+    pub async fn interrupt(&mut self, interruption: Interruption) {
+        match interruption {
+            Interruption::Forfeit(loser, termination) => {
+                self.send(loser, Lose(termination, Vec::new())).await;
+                self.send(loser.other(), Win(termination, Vec::new())).await;
+                self.broadcast(GameOver(Some(loser.other()), termination, Vec::new()))
+                    .await;
+            }
+            Interruption::Agreed => {
+                self.send(Red, Draw).await;
+                self.send(Yellow, Draw).await;
+                self.broadcast(GameOver(None, Termination::Agreement, Vec::new()))
+                    .await;
+            }
+            Interruption::Aborted | Interruption::Adjourned => {
+                let stopped = GameOver(None, Termination::Adjourned, Vec::new());
+                self.send(Red, stopped.clone()).await;
+                self.send(Yellow, stopped.clone()).await;
+                self.broadcast(stopped).await;
+            }
+        }
+    }
+
+    // Apply a command of the administrator, returns why the game ended if it did
+    async fn control(&mut self, control: Control) -> Result<(), Interruption> {
+        info!(?control, "command of the administrator");
+        match control {
+            Control::Kick(player) => {
+                let notice = Notice(String::from(
+                    "You were removed from the game by the server.",
+                ));
+                self.send(player, notice).await;
+                Err(Interruption::Forfeit(player, Termination::Abandoned))
+            }
+            Control::Abort => Err(Interruption::Aborted),
+            Control::Adjourn => Err(Interruption::Adjourned),
+            Control::Notice(text) => {
+                self.send(Red, Notice(text.clone())).await;
+                self.send(Yellow, Notice(text.clone())).await;
+                self.broadcast(Notice(text)).await;
+                Ok(())
+            }
+        }
+    }
+
+    // Offer a rematch to both players, returns true if both accepted it in time. If a player
+    // declines, leaves or does not answer in time, the players are told there is no rematch.
+    pub async fn ask_rematch(&mut self, timeout: Duration) -> bool {
+        let mut accepted = [false; 2];
+        let mut declined = self.seats.iter().any(|seat| seat.lost_since.is_some());
+        if !declined {
+            self.send(Red, OfferRematch).await;
+            self.send(Yellow, OfferRematch).await;
+        }
+        let deadline = Instant::now() + timeout;
+        while !declined && accepted != [true, true] {
+            let event = match time::timeout_at(deadline, self.events.recv()).await {
+                Ok(Some(event)) => event,
+                Ok(None) | Err(_) => break,
+            };
+            match event {
+                Event::Received(tag, number, message)
+                    if number == self.connection(self.tag(tag)) =>
+                {
+                    match message {
+                        Rematch(accept) => {
+                            accepted[tag.select(0, 1).0] = accept;
+                            declined = !accept;
+                        }
+                        Chat(text) => self.relay_chat(self.tag(tag), text).await,
+                        _ => (),
+                    }
+                }
+                Event::Disconnected(tag, number) if number == self.connection(self.tag(tag)) => {
+                    declined = true;
+                }
+                Event::Control(control) => declined = self.control(control).await.is_err(),
+                _ => (),
+            }
+        }
+        if accepted == [true, true] {
+            return true;
+        }
+        self.send(Red, NoRematch).await;
+        self.send(Yellow, NoRematch).await;
+        false
+    }
+}
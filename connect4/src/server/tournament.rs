@@ -0,0 +1,700 @@
+use super::{
+    lobby::{Entrant, BOT_PREFIX},
+    server::{play_game, Shared},
+};
+use crate::{
+    communication::{
+        transport::{ChannelEnd, Link},
+        Message::{self, *},
+    },
+    game_logic::Player::*,
+};
+use std::{
+    cmp::Ordering,
+    fmt, fs,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    net::{tcp::OwnedWriteHalf, TcpStream},
+    sync::mpsc::{self, UnboundedReceiver},
+    task, time,
+};
+use tracing::{error, info};
+
+// Maximum time to send a message to a player, beyond which the message is lost
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Number of additional pairs of games of a knockout match which is still tied, after which the
+// better seed goes through
+const TIEBREAK_PAIRS: usize = 2;
+
+// Points of a participant who is not paired in a round of a Swiss tournament, as for a drawn match
+const BYE_POINTS: f64 = 1.0;
+
+// Number of pairs tried to pair a Swiss round without rematches, beyond which rematches are
+// allowed so that the search stays short
+const MAX_PAIRING_TRIES: usize = 100_000;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Format {
+    RoundRobin,
+    Swiss(usize), // number of rounds
+    Knockout,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::RoundRobin => write!(f, "Round-robin"),
+            Format::Swiss(rounds) => write!(f, "Swiss ({} rounds)", rounds),
+            Format::Knockout => write!(f, "Knockout"),
+        }
+    }
+}
+
+// Tournament hosted by the server. It starts once the expected number of players registered, or
+// when the server starts if only bots take part.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TournamentSettings {
+    pub format: Format,
+    pub bots: Vec<u8>,               // levels of the bots taking part
+    pub players: usize,              // number of players who have to register
+    pub table_file: Option<PathBuf>, // standings are only printed without a file
+}
+
+// Connection of a registered player. Their messages are read by a dedicated task, so that they can
+// be relayed to their successive games.
+struct Human {
+    writer: OwnedWriteHalf,
+    inbox: UnboundedReceiver<Message>,
+}
+
+impl Human {
+    fn new(stream: TcpStream) -> Self {
+        let (mut reader, writer) = stream.into_split();
+        let (sender, inbox) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                match Message::read_async(&mut reader).await {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            return;
+                        }
+                    }
+                    Err(error) if error.kind() == ErrorKind::InvalidData => (),
+                    Err(_) => return,
+                }
+            }
+        });
+        Self { writer, inbox }
+    }
+
+    async fn send(&mut self, message: Message) -> bool {
+        matches!(
+            time::timeout(WRITE_TIMEOUT, message.write_async(&mut self.writer)).await,
+            Ok(Ok(()))
+        )
+    }
+
+    // Relay the messages between the player and a game until the game ends. If the connection is
+    // lost, the game notices it when the channel is dropped.
+    async fn relay(&mut self, mut game: ChannelEnd) {
+        // Messages sent between two games were meant for the previous one
+        while self.inbox.try_recv().is_ok() {}
+        loop {
+            tokio::select! {
+                message = game.receive() => match message {
+                    Ok(message) => {
+                        if !self.send(message).await {
+                            return;
+                        }
+                    }
+                    Err(_) => return,
+                },
+                message = self.inbox.recv() => match message {
+                    Some(message) => {
+                        let _ = game.send(message);
+                    }
+                    None => return,
+                },
+            }
+        }
+    }
+}
+
+enum Contender {
+    Bot(u8), // level
+    Player(Human),
+}
+
+struct Participant {
+    name: String,
+    contender: Contender,
+}
+
+impl Participant {
+    // Players who lost their connection lose their remaining games without playing them
+    fn connected(&self) -> bool {
+        match &self.contender {
+            Contender::Bot(_) => true,
+            Contender::Player(human) => !human.inbox.is_closed(),
+        }
+    }
+
+    // Entrant of a game, and the end of the channel to relay to the player
+    fn enter(&self, shared: &Shared) -> (Entrant, Option<ChannelEnd>) {
+        match &self.contender {
+            Contender::Bot(level) => {
+                let bot = shared.bots.spawn(*level).unwrap();
+                (Entrant::bot(bot, *level), None)
+            }
+            Contender::Player(_) => {
+                let (link, end) = ChannelEnd::pair();
+                let entrant = Entrant {
+                    account: Some(self.name.clone()),
+                    ..Entrant::local(Link::Channel(link))
+                };
+                (entrant, Some(end))
+            }
+        }
+    }
+
+    async fn relay(&mut self, game: Option<ChannelEnd>) {
+        if let (Contender::Player(human), Some(game)) = (&mut self.contender, game) {
+            human.relay(game).await;
+        }
+    }
+}
+
+// Play a game, returns the points of red and yellow
+async fn play(shared: &Shared, red: &mut Participant, yellow: &mut Participant) -> (f64, f64) {
+    match (red.connected(), yellow.connected()) {
+        (true, true) => (),
+        (red, yellow) => return (red as u8 as f64, yellow as u8 as f64),
+    }
+    let (red_entrant, red_game) = red.enter(shared);
+    let (yellow_entrant, yellow_game) = yellow.enter(shared);
+    let (winner, ..) = tokio::join!(
+        play_game((red_entrant, yellow_entrant), shared.clone()),
+        red.relay(red_game),
+        yellow.relay(yellow_game),
+    );
+    match winner {
+        Some(Red) => (1.0, 0.0),
+        Some(Yellow) => (0.0, 1.0),
+        None => (0.5, 0.5),
+    }
+}
+
+// Play two games, each participant having red once, and the tiebreak pairs of games if the match
+// is still tied. Returns the points of each participant in each game.
+async fn play_match(
+    shared: &Shared,
+    (first, second): (&mut Participant, &mut Participant),
+    tiebreak_pairs: usize,
+) -> Vec<(f64, f64)> {
+    let mut games = Vec::new();
+    for pair in 0..=tiebreak_pairs {
+        let total = |games: &Vec<(f64, f64)>| {
+            games.iter().fold((0.0, 0.0), |(first, second), game| {
+                (first + game.0, second + game.1)
+            })
+        };
+        let (first_points, second_points) = total(&games);
+        if pair > 0 && first_points != second_points {
+            break;
+        }
+        games.push(play(shared, first, second).await);
+        let (second_points, first_points) = play(shared, second, first).await;
+        games.push((first_points, second_points));
+    }
+    games
+}
+
+// Results of a participant. Opponents are counted once per match.
+#[derive(Clone, Debug, Default)]
+struct Record {
+    points: f64,
+    wins: u32,
+    games: u32,
+    byes: u32,
+    opponents: Vec<usize>,
+    results: Vec<(usize, f64)>, // opponent and points of each game
+    eliminated: Option<usize>,  // round in which a participant of a knockout lost
+}
+
+struct Standings {
+    names: Vec<String>,
+    records: Vec<Record>,
+}
+
+impl Standings {
+    fn new(names: Vec<String>) -> Self {
+        let records = vec![Record::default(); names.len()];
+        Self { names, records }
+    }
+
+    fn add_match(&mut self, (first, second): (usize, usize), games: &[(f64, f64)]) {
+        for &(points, opponent_points) in games {
+            for (player, opponent, points) in
+                [(first, second, points), (second, first, opponent_points)]
+            {
+                let record = &mut self.records[player];
+                record.points += points;
+                record.wins += (points == 1.0) as u32;
+                record.games += 1;
+                record.results.push((opponent, points));
+            }
+        }
+        self.records[first].opponents.push(second);
+        self.records[second].opponents.push(first);
+    }
+
+    fn add_bye(&mut self, player: usize, points: f64) {
+        self.records[player].points += points;
+        self.records[player].byes += 1;
+    }
+
+    // Sum of the points of the opponents
+    fn buchholz(&self, player: usize) -> f64 {
+        let record = &self.records[player];
+        record
+            .opponents
+            .iter()
+            .map(|&opponent| self.records[opponent].points)
+            .sum()
+    }
+
+    // Sum of the points of the opponents, weighted by the points scored against them
+    fn sonneborn_berger(&self, player: usize) -> f64 {
+        let record = &self.records[player];
+        record
+            .results
+            .iter()
+            .map(|&(opponent, points)| points * self.records[opponent].points)
+            .sum()
+    }
+
+    // Participants from the first to the last. Knockout participants are ranked by the round in
+    // which they lost first.
+    fn ranking(&self) -> Vec<usize> {
+        let key = |player: usize| {
+            let record = &self.records[player];
+            (
+                record.eliminated.unwrap_or(usize::MAX),
+                record.points,
+                self.sonneborn_berger(player),
+                self.buchholz(player),
+                record.wins,
+            )
+        };
+        let mut ranking: Vec<_> = (0..self.names.len()).collect();
+        ranking.sort_by(|&first, &second| {
+            key(second)
+                .partial_cmp(&key(first))
+                .unwrap_or(Ordering::Equal)
+        });
+        ranking
+    }
+
+    // Pair the participants with the closest scores who did not meet yet. The lowest ranked
+    // participant who did not have a bye yet is not paired if their number is odd.
+    fn swiss_pairings(&self) -> (Vec<(usize, usize)>, Option<usize>) {
+        let mut unpaired = self.ranking();
+        let bye = if unpaired.len() % 2 == 1 {
+            let position = unpaired
+                .iter()
+                .rposition(|&player| self.records[player].byes == 0)
+                .unwrap_or(unpaired.len() - 1);
+            Some(unpaired.remove(position))
+        } else {
+            None
+        };
+        // A rematch is only played when the participants cannot all meet new opponents, or when
+        // no such pairing was found in time. With rematches, the first pairing tried is valid.
+        let (mut tries, mut rematch_tries) = (MAX_PAIRING_TRIES, MAX_PAIRING_TRIES);
+        let pairings = self
+            .pair(&unpaired, false, &mut tries)
+            .or_else(|| self.pair(&unpaired, true, &mut rematch_tries))
+            .unwrap();
+        (pairings, bye)
+    }
+
+    // Pair each participant in order with the next one they can play, so that the remaining ones
+    // can still be paired, or None if there is no such pairing within the given number of tries
+    fn pair(
+        &self,
+        participants: &[usize],
+        rematches: bool,
+        tries: &mut usize,
+    ) -> Option<Vec<(usize, usize)>> {
+        let Some((&player, others)) = participants.split_first() else {
+            return Some(Vec::new());
+        };
+        for (index, &opponent) in others.iter().enumerate() {
+            if !rematches && self.records[player].opponents.contains(&opponent) {
+                continue;
+            }
+            if *tries == 0 {
+                return None;
+            }
+            *tries -= 1;
+            let mut remaining = others.to_vec();
+            remaining.remove(index);
+            if let Some(mut pairings) = self.pair(&remaining, rematches, tries) {
+                pairings.insert(0, (player, opponent));
+                return Some(pairings);
+            }
+        }
+        None
+    }
+
+    fn table(&self, format: Format) -> String {
+        let mut table = format!(
+            "{} tournament, {} participants\n\n{:>4}  {:<20} {:>6} {:>9} {:>9} {:>5} {:>6}\n",
+            format,
+            self.names.len(),
+            "Rank",
+            "Participant",
+            "Points",
+            "S-B",
+            "Buchholz",
+            "Wins",
+            "Games"
+        );
+        for (rank, player) in self.ranking().into_iter().enumerate() {
+            let record = &self.records[player];
+            table += &format!(
+                "{:>4}  {:<20} {:>6.1} {:>9.2} {:>9.1} {:>5} {:>6}\n",
+                rank + 1,
+                self.names[player],
+                record.points,
+                self.sonneborn_berger(player),
+                self.buchholz(player),
+                record.wins,
+                record.games
+            );
+        }
+        table
+    }
+}
+
+// Rounds of a round-robin tournament with the circle method: the first participant stays in place
+// while the others rotate. With an odd number of participants, the one paired with None rests.
+fn round_robin(participants: usize) -> Vec<Vec<(usize, usize)>> {
+    let mut circle: Vec<_> = (0..participants).map(Some).collect();
+    if participants % 2 == 1 {
+        circle.push(None);
+    }
+    let size = circle.len();
+    let mut rounds = Vec::new();
+    for _ in 1..size {
+        let round = (0..size / 2)
+            .filter_map(|index| match (circle[index], circle[size - 1 - index]) {
+                (Some(first), Some(second)) => Some((first, second)),
+                _ => None,
+            })
+            .collect();
+        rounds.push(round);
+        circle[1..].rotate_right(1);
+    }
+    rounds
+}
+
+// Play the matches of a round at the same time. Each participant plays at most one match.
+async fn play_round(
+    shared: &Shared,
+    participants: &mut [Option<Participant>],
+    pairings: &[(usize, usize)],
+    tiebreak_pairs: usize,
+) -> Vec<((usize, usize), Vec<(f64, f64)>)> {
+    let matches: Vec<_> = pairings
+        .iter()
+        .map(|&(first, second)| {
+            let mut players = (
+                participants[first].take().unwrap(),
+                participants[second].take().unwrap(),
+            );
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                let games =
+                    play_match(&shared, (&mut players.0, &mut players.1), tiebreak_pairs).await;
+                (players, games)
+            })
+        })
+        .collect();
+    let mut results = Vec::new();
+    for (handle, &(first, second)) in matches.into_iter().zip(pairings) {
+        let (players, games) = handle.await.unwrap();
+        participants[first] = Some(players.0);
+        participants[second] = Some(players.1);
+        results.push(((first, second), games));
+    }
+    results
+}
+
+// Run the tournament between the bots and the registered players
+pub async fn run(shared: Shared, settings: TournamentSettings, players: Players) {
+    let mut participants: Vec<_> = settings
+        .bots
+        .iter()
+        .map(|&level| Participant {
+            name: format!("{}{}", BOT_PREFIX, level),
+            contender: Contender::Bot(level),
+        })
+        .chain(players.into_iter().map(|(name, stream)| Participant {
+            name,
+            contender: Contender::Player(Human::new(stream)),
+        }))
+        .map(Some)
+        .collect();
+    let names = participants
+        .iter()
+        .map(|participant| participant.as_ref().unwrap().name.clone())
+        .collect();
+    info!(format = %settings.format, participants = participants.len(), "tournament started");
+    let mut standings = Standings::new(names);
+    let export = |standings: &Standings| {
+        if let Some(file) = &settings.table_file {
+            if let Err(error) = fs::write(file, standings.table(settings.format)) {
+                error!(%error, "unable to save the standings of the tournament");
+            }
+        }
+    };
+    match settings.format {
+        Format::RoundRobin => {
+            for pairings in round_robin(participants.len()) {
+                if shared.registry.closed() {
+                    return;
+                }
+                for (pairing, games) in play_round(&shared, &mut participants, &pairings, 0).await {
+                    standings.add_match(pairing, &games);
+                }
+                export(&standings);
+            }
+        }
+        Format::Swiss(rounds) => {
+            for _ in 0..rounds {
+                if shared.registry.closed() {
+                    return;
+                }
+                // The search of the pairings may take a while
+                let (searched, (pairings, bye)) = task::spawn_blocking(move || {
+                    let pairings = standings.swiss_pairings();
+                    (standings, pairings)
+                })
+                .await
+                .unwrap();
+                standings = searched;
+                if let Some(player) = bye {
+                    standings.add_bye(player, BYE_POINTS);
+                }
+                for (pairing, games) in play_round(&shared, &mut participants, &pairings, 0).await {
+                    standings.add_match(pairing, &games);
+                }
+                export(&standings);
+            }
+        }
+        Format::Knockout => {
+            // Participants are seeded in order, the best seed rests when their number is odd
+            let mut remaining: Vec<_> = (0..participants.len()).collect();
+            let mut round = 1;
+            while remaining.len() > 1 {
+                if shared.registry.closed() {
+                    return;
+                }
+                let offset = remaining.len() % 2;
+                let pairings: Vec<_> = (0..remaining.len() / 2)
+                    .map(|index| {
+                        (
+                            remaining[offset + index],
+                            remaining[remaining.len() - 1 - index],
+                        )
+                    })
+                    .collect();
+                for (pairing, games) in
+                    play_round(&shared, &mut participants, &pairings, TIEBREAK_PAIRS).await
+                {
+                    standings.add_match(pairing, &games);
+                    let points = games
+                        .iter()
+                        .fold(0.0, |points, game| points + game.0 - game.1);
+                    let loser = if points < 0.0 { pairing.0 } else { pairing.1 };
+                    standings.records[loser].eliminated = Some(round);
+                    remaining.retain(|&player| player != loser);
+                }
+                export(&standings);
+                round += 1;
+            }
+        }
+    }
+    let table = standings.table(settings.format);
+    info!("tournament over");
+    println!("{}", table);
+    let ranking = standings.ranking();
+    for (player, participant) in participants.into_iter().enumerate() {
+        if let Some(Participant {
+            contender: Contender::Player(mut human),
+            ..
+        }) = participant
+        {
+            let rank = ranking.iter().position(|&other| other == player).unwrap();
+            human
+                .send(TournamentOver(rank as u16 + 1, ranking.len() as u16))
+                .await;
+        }
+    }
+}
+
+// Names and connections of the players registered to the tournament
+pub type Players = Vec<(String, TcpStream)>;
+
+// Players registered to the tournament, and the ones being told that they are, until it starts
+#[derive(Default)]
+struct Entries {
+    players: Players,
+    reserved: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct Registrations(Arc<Mutex<Option<Entries>>>);
+
+impl Registrations {
+    // Registrations are closed if there is no tournament or no player is expected
+    pub fn new(settings: Option<&TournamentSettings>) -> Self {
+        let open = settings.is_some_and(|settings| settings.players > 0);
+        Self(Arc::new(Mutex::new(open.then(Entries::default))))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        match &*self.0.lock().unwrap() {
+            Some(entries) => entries
+                .players
+                .iter()
+                .map(|(name, _)| name.clone())
+                .chain(entries.reserved.iter().cloned())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Close the registrations, the players who registered are disconnected
+    pub fn close(&self) {
+        self.0.lock().unwrap().take();
+    }
+
+    // Keep a place for a player while they are told that they are registered, returns false if
+    // the registrations are closed, full or if the player already registered
+    pub fn reserve(&self, name: &str, expected: usize) -> bool {
+        match &mut *self.0.lock().unwrap() {
+            Some(entries)
+                if entries.players.len() + entries.reserved.len() < expected
+                    && entries.players.iter().all(|(player, _)| player != name)
+                    && !entries.reserved.iter().any(|player| player == name) =>
+            {
+                entries.reserved.push(name.to_owned());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Give the place back, when the player could not be told that they are registered
+    pub fn cancel(&self, name: &str) {
+        if let Some(entries) = &mut *self.0.lock().unwrap() {
+            entries.reserved.retain(|player| player != name);
+        }
+    }
+
+    // Add the connection of a player who reserved a place and return all the players once the
+    // last expected one registered. The connection is given back if the registrations closed in
+    // the meantime.
+    pub fn register(
+        &self,
+        name: String,
+        stream: TcpStream,
+        expected: usize,
+    ) -> Result<Option<Players>, TcpStream> {
+        let mut registrations = self.0.lock().unwrap();
+        match registrations.as_mut() {
+            Some(entries) => {
+                entries.reserved.retain(|player| *player != name);
+                entries.players.push((name, stream));
+                Ok(if entries.players.len() == expected {
+                    registrations.take().map(|entries| entries.players)
+                } else {
+                    None
+                })
+            }
+            None => Err(stream),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Participants ranked in the order of their numbers, with the given matches already played
+    fn standings(participants: usize, played: &[(usize, usize)]) -> Standings {
+        let names = (0..participants).map(|player| player.to_string()).collect();
+        let mut standings = Standings::new(names);
+        for &(first, second) in played {
+            standings.records[first].opponents.push(second);
+            standings.records[second].opponents.push(first);
+        }
+        standings
+    }
+
+    #[test]
+    fn swiss_pairs_the_closest_new_opponents() {
+        let (pairings, bye) = standings(4, &[(0, 1)]).swiss_pairings();
+        assert_eq!(pairings, vec![(0, 2), (1, 3)]);
+        assert_eq!(bye, None);
+    }
+
+    #[test]
+    fn swiss_backtracks_instead_of_repeating_a_match() {
+        // Pairing 0 with 1 would leave 2 and 3, who already met
+        let (pairings, _) = standings(4, &[(2, 3)]).swiss_pairings();
+        assert_eq!(pairings, vec![(0, 2), (1, 3)]);
+        let (pairings, _) = standings(4, &[(0, 2), (2, 3), (0, 3)]).swiss_pairings();
+        assert_eq!(pairings, vec![(0, 1), (2, 3)]);
+    }
+
+    #[test]
+    fn swiss_repeats_a_match_only_when_every_pairing_does() {
+        let (pairings, _) = standings(2, &[(0, 1)]).swiss_pairings();
+        assert_eq!(pairings, vec![(0, 1)]);
+        let played = [(0, 1), (2, 3), (0, 2), (1, 3), (0, 3), (1, 2)];
+        let (pairings, _) = standings(4, &played).swiss_pairings();
+        assert_eq!(pairings.len(), 2);
+    }
+
+    #[test]
+    fn swiss_stops_searching_without_rematches_after_a_while() {
+        // The first 13 participants only met the last 11, so one of them has to play a rematch
+        let played: Vec<_> = (0..13)
+            .flat_map(|first| (13..24).map(move |second| (first, second)))
+            .collect();
+        let (pairings, _) = standings(24, &played).swiss_pairings();
+        let mut paired: Vec<_> = pairings
+            .iter()
+            .flat_map(|&(first, second)| [first, second])
+            .collect();
+        paired.sort();
+        assert_eq!(paired, (0..24).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn swiss_gives_the_bye_to_the_lowest_participant_without_one() {
+        let mut standings = standings(3, &[]);
+        standings.records[2].byes = 1;
+        let (pairings, bye) = standings.swiss_pairings();
+        assert_eq!(bye, Some(1));
+        assert_eq!(pairings, vec![(0, 2)]);
+    }
+}
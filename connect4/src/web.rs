@@ -0,0 +1,126 @@
+use crate::{
+    ai,
+    game_logic::{Cell, Connect4, BOARD_HEIGHT, BOARD_WIDTH},
+};
+use wasm_bindgen::prelude::*;
+
+// Game played in a browser, the page draws the board and asks the ai for its moves
+#[wasm_bindgen]
+pub struct Game {
+    game: Connect4,
+    history: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Game {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            game: Connect4::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn width() -> usize {
+        BOARD_WIDTH
+    }
+
+    pub fn height() -> usize {
+        BOARD_HEIGHT
+    }
+
+    // Place a token of the player to move in the column, counted from 0. Returns false if the
+    // game is over or the column is full.
+    pub fn play(&mut self, column: usize) -> bool {
+        if self.game.over() || !self.game.valid_action(column) {
+            return false;
+        }
+        self.game.play(column);
+        self.history.push(column as u8);
+        true
+    }
+
+    // Take back the last move, returns false if no move was played
+    pub fn undo(&mut self) -> bool {
+        if self.history.pop().is_none() {
+            return false;
+        }
+        self.game = Connect4::from_moves(&self.history).unwrap();
+        true
+    }
+
+    pub fn valid(&self, column: usize) -> bool {
+        !self.game.over() && self.game.valid_action(column)
+    }
+
+    // Cells of the board from the bottom left corner, row after row: 0 when empty, 1 for red and
+    // 2 for yellow
+    pub fn board(&self) -> Vec<u8> {
+        self.game
+            .board
+            .iter()
+            .map(|cell| match cell {
+                Cell::Empty => 0,
+                Cell::Red => 1,
+                Cell::Yellow => 2,
+            })
+            .collect()
+    }
+
+    // Columns played so far, counted from 0
+    pub fn moves(&self) -> Vec<u8> {
+        self.history.clone()
+    }
+
+    #[wasm_bindgen(js_name = toPlay)]
+    pub fn to_play(&self) -> String {
+        self.game.to_play.to_string()
+    }
+
+    pub fn over(&self) -> bool {
+        self.game.over()
+    }
+
+    // "red" or "yellow" when a player won the game, undefined otherwise
+    pub fn winner(&self) -> Option<String> {
+        self.game.check_winner().map(|winner| winner.to_string())
+    }
+
+    // Indices in the board of the tokens of the lines completed by the winner
+    #[wasm_bindgen(js_name = winningCells)]
+    pub fn winning_cells(&self) -> Vec<usize> {
+        self.game
+            .winning_lines()
+            .concat()
+            .into_iter()
+            .map(|(row, column)| BOARD_WIDTH * row + column)
+            .collect()
+    }
+
+    // Column chosen by the ai for the player to move, the search runs on the thread of the page
+    // so it should stay shallow
+    #[wasm_bindgen(js_name = aiMove)]
+    pub fn ai_move(&self, depth: usize) -> Option<usize> {
+        (!self.game.over() && depth > 0).then(|| ai::action(self.game.clone(), depth))
+    }
+
+    // Score of each column for the player to move, undefined for the full columns
+    pub fn scores(&self, depth: usize) -> Vec<JsValue> {
+        if self.game.over() || depth == 0 {
+            return Vec::new();
+        }
+        ai::column_scores(&self.game, depth)
+            .iter()
+            .map(|score| match score {
+                Some(score) => JsValue::from_str(&ai::describe_score(*score)),
+                None => JsValue::UNDEFINED,
+            })
+            .collect()
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
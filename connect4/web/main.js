@@ -0,0 +1,112 @@
+// Page of the browser client: the game and the AI run in the WebAssembly module built in pkg/
+import init, { Game } from "./pkg/connect4.js";
+
+await init();
+
+const width = Game.width();
+const height = Game.height();
+const board = document.getElementById("board");
+const status = document.getElementById("status");
+const color = document.getElementById("color");
+const depth = document.getElementById("depth");
+let game = new Game();
+
+// Depth of the search of the AI, within the bounds of the field whatever was typed in it
+function aiDepth() {
+  const [min, max] = [Number(depth.min), Number(depth.max)];
+  const value = Math.round(Number(depth.value));
+  const clamped = Number.isFinite(value) ? Math.min(Math.max(value, min), max) : min;
+  depth.value = clamped;
+  return clamped;
+}
+
+// The AI moves when it is not the turn of a human
+function aiToPlay() {
+  return !game.over() && color.value !== "both" && game.toPlay() !== color.value;
+}
+
+function render(text) {
+  const cells = game.board();
+  const winning = game.winningCells();
+  const moves = game.moves();
+  board.replaceChildren();
+  // The board is stored from the bottom row, the page draws it from the top
+  for (let row = height - 1; row >= 0; row--) {
+    for (let column = 0; column < width; column++) {
+      const index = row * width + column;
+      const cell = document.createElement("div");
+      cell.className = "cell " + ["", "red", "yellow"][cells[index]];
+      if (winning.includes(index)) {
+        cell.classList.add("winning");
+      }
+      cell.onclick = () => play(column);
+      board.appendChild(cell);
+    }
+  }
+  if (moves.length > 0 && winning.length === 0) {
+    const last = moves[moves.length - 1];
+    const row = cells.filter((cell, index) => index % width === last && cell !== 0).length - 1;
+    board.children[(height - 1 - row) * width + last].classList.add("last");
+  }
+  if (text !== undefined) {
+    status.textContent = text;
+  } else if (game.winner() !== undefined) {
+    status.textContent = `The ${game.winner()} player won the game.`;
+  } else if (game.over()) {
+    status.textContent = "The game ended in a draw.";
+  } else {
+    status.textContent = `The ${game.toPlay()} player is to move.`;
+  }
+}
+
+// Let the page draw the board before the search of the AI blocks it
+function playAi() {
+  if (!aiToPlay()) {
+    return;
+  }
+  render("The AI is thinking...");
+  setTimeout(() => {
+    const column = game.aiMove(aiDepth());
+    if (column !== undefined) {
+      game.play(column);
+    }
+    render();
+  }, 20);
+}
+
+function play(column) {
+  if (aiToPlay() || !game.play(column)) {
+    return;
+  }
+  render();
+  playAi();
+}
+
+document.getElementById("new").onclick = () => {
+  game = new Game();
+  render();
+  playAi();
+};
+
+// Take back the moves of the AI too, so that it is the turn of the human again
+document.getElementById("undo").onclick = () => {
+  game.undo();
+  while (aiToPlay() && game.moves().length > 0) {
+    game.undo();
+  }
+  render();
+  playAi();
+};
+
+document.getElementById("hint").onclick = () => {
+  if (game.over() || aiToPlay()) {
+    return;
+  }
+  const column = game.aiMove(aiDepth());
+  if (column !== undefined) {
+    render(`The AI suggests column ${column + 1}.`);
+  }
+};
+
+color.onchange = playAi;
+render();